@@ -0,0 +1,79 @@
+//! Compares the time taken to build dijkstra maps with the original algorithm, which scanned the list of
+//! unvisited tiles for every tile it labelled, and the current one. Both must give the same values on every
+//! map timed.
+//!
+//! Run with `cargo run --release --example dijkstra_benchmark`. The original algorithm is quadratic in map
+//! area, so the largest map takes a while.
+
+extern crate spellbound;
+
+use std::time::Instant;
+use spellbound::dijkstra_map::{DijkstraMap,DijkstraTile};
+use spellbound::dungeon::Map;
+use spellbound::grid::Grid;
+use spellbound::rng::GameRng;
+
+/// The original construction algorithm, kept here for comparison
+fn old_dijkstra_map(map: &[Vec<DijkstraTile>]) -> Vec<Vec<DijkstraTile>> {
+	let mut output = vec![vec![DijkstraTile::Impassable; map[0].len()];map.len()];
+	let mut current_nodes = vec![];
+	let mut next_nodes = vec![];
+	let mut unvisited = vec![];
+	for (row_index, row) in map.iter().enumerate() {
+		for (column_index, tile) in row.iter().enumerate() {
+			match *tile {
+				DijkstraTile::Goal => current_nodes.push((column_index,row_index)),
+				DijkstraTile::Passable => unvisited.push((column_index,row_index)),
+				DijkstraTile::Value(_) => unvisited.push((column_index,row_index)),
+				_ => (),
+			}
+		}
+	}
+	let mut distance = 0isize;
+	while !current_nodes.is_empty() {
+		for (i,j) in current_nodes {
+			output[j][i] = DijkstraTile::Value(distance as f64);
+			for (i2,j2) in unvisited.clone() { if (i as isize - i2 as isize).abs() <= 1 && (j as isize - j2 as isize ).abs() <= 1 { next_nodes.push((i2,j2)) } };
+			unvisited.retain(|&(i2,j2)|(i as isize -i2 as isize ).abs() > 1 || (j as isize - j2 as isize ).abs() > 1);
+		}
+		current_nodes = next_nodes.clone();
+		next_nodes = vec![];
+		distance += 1;
+	}
+	output
+}
+
+/// Average time in milliseconds taken by the given function over a number of runs
+fn time<F: FnMut()>(runs: u32, mut f: F) -> f64 {
+	let start = Instant::now();
+	for _ in 0..runs { f() }
+	let elapsed = start.elapsed();
+	(elapsed.as_secs() as f64 * 1e3 + elapsed.subsec_nanos() as f64 * 1e-6) / runs as f64
+}
+
+fn main() {
+	println!("{:>8} {:>14} {:>14}", "size", "old (ms)", "new (ms)");
+	for &(size,runs) in [(42,20),(100,3),(250,1)].iter() {
+		let map = Map::new(size, &mut GameRng::new(size as u64));
+		// A single goal in the corner, where the player starts
+		let mut tiles: Grid<DijkstraTile> = map.grid.map(|tile| match tile.is_passable() {
+			true => DijkstraTile::Passable,
+			false => DijkstraTile::Impassable,
+		});
+		tiles[(1,1)] = DijkstraTile::Goal;
+		// The original algorithm takes a vector of rows
+		let rows: Vec<Vec<DijkstraTile>> = tiles.rows().map(|row| row.to_vec()).collect();
+
+		let old = time(runs, || { old_dijkstra_map(&rows); });
+		let new = time(runs, || { DijkstraMap::new(&tiles); });
+		let (old_map, new_map) = (old_dijkstra_map(&rows), DijkstraMap::new(&tiles));
+		for c in tiles.coords() {
+			let old_value = match old_map[c.1][c.0] {
+				DijkstraTile::Value(v) => Some(v),
+				_ => None,
+			};
+			assert_eq!(old_value, new_map.value(c), "{}x{}: the algorithms disagree at {:?}", size, size, c);
+		}
+		println!("{:>8} {:>14.3} {:>14.3}", format!("{}x{}", size, size), old, new);
+	}
+}
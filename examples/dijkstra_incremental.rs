@@ -0,0 +1,67 @@
+//! Measures how long updating a dijkstra map in place takes, next to building it again, on generated levels
+//! of a few sizes. The tests in `dijkstra_map` check that the updates give the same values as a rebuild.
+//!
+//! Run with `cargo run --release --example dijkstra_incremental`.
+
+extern crate rand;
+extern crate spellbound;
+
+use std::time::Instant;
+use rand::Rng;
+use spellbound::dijkstra_map::DijkstraMap;
+use spellbound::dungeon::Map;
+use spellbound::grid::{Coord,Grid};
+use spellbound::rng::GameRng;
+
+/// Elapsed time in milliseconds
+fn millis(start: Instant) -> f64 {
+	let elapsed = start.elapsed();
+	elapsed.as_secs() as f64 * 1e3 + elapsed.subsec_nanos() as f64 * 1e-6
+}
+
+fn main() {
+	// Updating takes time proportional to the number of tiles whose values change. A door closing
+	// only changes the rooms behind it, so it is much cheaper than a rebuild. A lone goal taking a step
+	// changes nearly every value on the level, and is slower than a rebuild, which can use a breadth first
+	// search.
+	println!("{:>8} {:>14} {:>14} {:>14}", "size", "rebuild (ms)", "step (ms)", "door (ms)");
+	for &size in [42, 100, 250].iter() {
+		let mut rng = GameRng::new(size as u64);
+		let level = Map::new(size, &mut rng);
+		let costs = level.grid.map(|tile| match tile.is_passable() {
+			true => Some(tile.cost()),
+			false => None,
+		});
+		let doors: Vec<Coord> = costs.coords().filter(|&c| costs[c].is_some_and(|cost| cost != 1.0)).collect();
+		let mut seeds = Grid::new(costs.width(), costs.height(), None);
+		seeds[(1,1)] = Some(0.0);
+		let runs = 100;
+
+		let start = Instant::now();
+		for _ in 0..runs { DijkstraMap::from_costs(&costs, &seeds); }
+		let rebuild = millis(start) / runs as f64;
+
+		// The goal wanders one tile at a time
+		let mut map = DijkstraMap::from_costs(&costs, &seeds);
+		let mut goal = (1,1);
+		let start = Instant::now();
+		for _ in 0..runs {
+			let next: Vec<Coord> = costs.neighbours(goal).filter(|&n| costs[n].is_some()).collect();
+			let next = next[rng.gen_range(0, next.len())];
+			map.add_goal(next);
+			map.remove_goal(goal);
+			goal = next;
+		}
+		let step = millis(start) / runs as f64;
+
+		// A door closes and opens again
+		let start = Instant::now();
+		for _ in 0..runs {
+			let door = doors[rng.gen_range(0, doors.len())];
+			map.set_cost(door, None);
+			map.set_cost(door, costs[door]);
+		}
+		let door = millis(start) / (2 * runs) as f64;
+		println!("{:>8} {:>14.3} {:>14.3} {:>14.3}", format!("{}x{}", size, size), rebuild, step, door);
+	}
+}
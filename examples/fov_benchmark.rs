@@ -0,0 +1,91 @@
+//! Compares the speed of the field of view with the line-drawing field of view it replaced. The tests in
+//! `fov` check what it sees.
+//!
+//! Run with `cargo run --release --example fov_benchmark`.
+
+extern crate spellbound;
+
+use std::time::Instant;
+use spellbound::dungeon::{Map,VISION_RADIUS,fov};
+use spellbound::generator::GeneratorKind;
+use spellbound::grid::Coord;
+use spellbound::rng::GameRng;
+
+/// The line of sight check the old field of view was built on. Returns the first tile blocking sight on the
+/// way to (x1,y1), or (x1,y1) itself if nothing does. Lines are not symmetric.
+fn los(map: &Map, x0:isize, y0:isize, x1:isize, y1:isize) -> Option<Coord> {
+	if x1 < 0 || y1 < 0 || x1 >= map.width() as isize || y1 >= map.height() as isize { return None };
+	let dx = x1 - x0;
+	let dy = y1 - y0;
+	if dx == 0 && dy == 0 { return Some((x0 as usize,y0 as usize)) };
+	let sx = match x0 < x1 {
+		true => 1,
+		false => -1,
+	};
+	let sy = match y0 < y1 {
+		true => 1,
+		false => -1,
+	};
+	// sx and sy are switches that enable us to compute the LOS in a single quarter of x/y plan
+	let mut xnext = x0;
+	let mut ynext = y0;
+	let denom = ((dx * dx + dy * dy) as f64).sqrt();
+	while xnext != x1 || ynext != y1 {
+		// The viewer's own tile never blocks sight
+		if (xnext, ynext) != (x0, y0) && !map.tile((xnext as usize, ynext as usize)).is_transvisible() {
+			return Some((xnext as usize, ynext as usize))
+		}
+		// Line-to-point distance formula < 0.5
+		if ((dy * (xnext - x0 + sx) - dx * (ynext - y0)) as f64).abs() / denom < 0.5 { xnext += sx; }
+		else if ((dy * (xnext - x0) - dx * (ynext - y0 + sy)) as f64).abs() / denom < 0.5 { ynext += sy; }
+		else
+		{
+			xnext += sx;
+			ynext += sy;
+		}
+	}
+	Some((x1 as usize, y1 as usize))
+}
+
+/// The field of view used before, which draws a separate line to every tile in range
+fn old_fov(map: &Map, x: isize, y: isize, radius: isize) -> Vec<Coord> {
+	let mut output = vec![];
+	for i in -radius..radius+1 {
+		for j in -radius..radius+1 {
+			if i*i+j*j < radius*radius {
+				if let Some(c) = los(map, x, y, x+i, y+j) { output.push(c) }
+			}
+		}
+	}
+	output
+}
+
+/// Elapsed time in milliseconds
+fn millis(start: Instant) -> f64 {
+	let elapsed = start.elapsed();
+	elapsed.as_secs() as f64 * 1e3 + elapsed.subsec_nanos() as f64 * 1e-6
+}
+
+fn main() {
+	// The field of view from every passable tile of a level
+	let map = Map::level(42, 1, GeneratorKind::RecursiveSplit, &mut GameRng::new(1));
+	let viewers: Vec<Coord> = map.grid.coords().filter(|&c| map.tile(c).is_passable()).collect();
+	println!("{:>8} {:>12} {:>12}", "radius", "old (us)", "new (us)");
+	for &radius in [5, 8, VISION_RADIUS as isize, 20].iter() {
+		let start = Instant::now();
+		for &(i,j) in viewers.iter() { old_fov(&map, i as isize, j as isize, radius); }
+		let old = millis(start) * 1e3 / viewers.len() as f64;
+		let start = Instant::now();
+		for &(i,j) in viewers.iter() { fov(&map, i as isize, j as isize, radius); }
+		let new = millis(start) * 1e3 / viewers.len() as f64;
+		println!("{:>8} {:>12.2} {:>12.2}", radius, old, new);
+	}
+
+	// The map of tiles the player cannot see used to draw a line to every tile of the level on each turn. It
+	// now reuses the field of view computed for the player.
+	let start = Instant::now();
+	for &(x,y) in viewers.iter() {
+		for (i,j) in map.grid.coords() { los(&map, x as isize, y as isize, i as isize, j as isize); }
+	}
+	println!("lines to every tile of the level: {:.2} us", millis(start) * 1e3 / viewers.len() as f64);
+}
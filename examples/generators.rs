@@ -0,0 +1,64 @@
+//! Shows a level made by each generator and prints the average number of rooms, doors and dead ends of each
+//! over many seeds. For the recursive splitter, which joins rooms with doors, it also counts how often
+//! `repair_doors` had work to do. The checks that every level is connected live in the generator's tests.
+//!
+//! Run with `cargo run --release --example generators`. Pass a generator name to only show that one.
+
+extern crate spellbound;
+
+use std::time::Instant;
+use spellbound::dungeon::TerrainType;
+use spellbound::generator::{Generator,GeneratorKind,RecursiveSplit,Stats,START,repair_doors,stats};
+use spellbound::grid::Grid;
+use spellbound::rng::GameRng;
+
+const SIZE: usize = 42;
+const SEEDS: u64 = 1000;
+const SPLIT_SEEDS: u64 = 10000;
+
+fn show(terrain: &Grid<TerrainType>) {
+	for row in terrain.rows() {
+		let line: String = row.iter().map(|t| t.glyph()).collect();
+		println!("{}", line);
+	}
+}
+
+fn main() {
+	let only = std::env::args().nth(1);
+	for kind in GeneratorKind::all().iter() {
+		if only.as_ref().is_some_and(|name| name != kind.name()) { continue }
+		println!("{}:", kind.name());
+		show(&kind.generate(SIZE, SIZE, &mut GameRng::new(1)));
+
+		let mut floor = 0;
+		let mut total = Stats::default();
+		let start = Instant::now();
+		for seed in 0..SEEDS {
+			let terrain = kind.generate(SIZE, SIZE, &mut GameRng::new(seed));
+			floor += terrain.iter().filter(|t| t.is_passable()).count();
+			let s = stats(&terrain, START);
+			total.rooms += s.rooms;
+			total.doors += s.doors;
+			total.dead_ends += s.dead_ends;
+		}
+		let elapsed = start.elapsed();
+		let ms = elapsed.as_secs() as f64 * 1e3 + elapsed.subsec_nanos() as f64 * 1e-6;
+		println!("{} seeds, {:.0}% passable on average, {:.3} ms per level", SEEDS, 100.0 * floor as f64 / (SEEDS as usize * SIZE * SIZE) as f64, ms / SEEDS as f64);
+		println!("{:.1} rooms, {:.1} doors and {:.1} dead ends on average\n", total.rooms as f64 / SEEDS as f64, total.doors as f64 / SEEDS as f64, total.dead_ends as f64 / SEEDS as f64);
+	}
+
+	// The recursive splitter on its own, over many more seeds and level sizes, to see how often its output
+	// needs repairing
+	if only.as_ref().is_some_and(|name| name != GeneratorKind::RecursiveSplit.name()) { return }
+	let (mut levels, mut broken, mut blocked, mut added) = (0, 0, 0, 0);
+	for seed in 0..SPLIT_SEEDS {
+		let (width, height) = (12 + seed as usize % 60, 12 + (seed as usize / 60) % 60);
+		let mut terrain = RecursiveSplit.generate(width, height, &mut GameRng::new(seed));
+		let before = stats(&terrain, START);
+		levels += 1;
+		if before.unreachable > 0 || before.blocked_doors > 0 { broken += 1 }
+		blocked += before.blocked_doors;
+		added += repair_doors(&mut terrain, START);
+	}
+	println!("split, {} levels of 12x12 to 71x71: {} needed repair ({} doors leading nowhere removed, {} doors added)", levels, broken, blocked, added);
+}
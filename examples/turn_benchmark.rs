@@ -0,0 +1,34 @@
+//! Measures how long the game takes to run a turn, grouped by how many monsters are on the level. Monsters
+//! share dijkstra maps through the map cache, so the time per turn should barely grow with their number.
+//!
+//! Run with `cargo run --release --example turn_benchmark`.
+
+extern crate spellbound;
+
+use std::time::Instant;
+use spellbound::game::{Command,Game};
+
+fn main() {
+	// Total time, number of turns and maps built, for each group of five monster counts
+	let mut groups = [(0.0, 0u32, 0u64); 5];
+	for seed in 0..20 {
+		let mut game = Game::new(seed);
+		for _ in 0..200 {
+			if game.is_over() { break }
+			let group = (game.creatures().len() / 5).min(4);
+			let builds = game.map_cache().builds();
+			let start = Instant::now();
+			game.step(Command::Automove);
+			let elapsed = start.elapsed();
+			groups[group].0 += elapsed.as_secs() as f64 * 1e3 + elapsed.subsec_nanos() as f64 * 1e-6;
+			groups[group].1 += 1;
+			groups[group].2 += game.map_cache().builds() - builds;
+		}
+	}
+
+	println!("{:>10} {:>8} {:>14} {:>16}", "monsters", "turns", "ms per turn", "maps per turn");
+	for (n,&(total,turns,builds)) in groups.iter().enumerate() {
+		if turns == 0 { continue }
+		println!("{:>10} {:>8} {:>14.3} {:>16.2}", format!("{}-{}", n*5, n*5+4), turns, total / turns as f64, builds as f64 / turns as f64);
+	}
+}
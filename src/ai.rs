@@ -0,0 +1,147 @@
+//! Monster decision making. Every monster type declares desires: weighted names of dijkstra maps. Each action,
+//! a creature picks a mood, and the desires for that mood are multiplied by their weights and summed into a
+//! single map that it walks down. Behaviors only add the few things a map cannot express, such as attacking
+//! or stealing when next to the player.
+//!
+//! A creature only hunts once it has noticed the player. Until then it is idle and follows its idle desires;
+//! after losing sight of the player, or hearing them, it searches where they were.
+
+use std::collections::HashMap;
+use dijkstra_map::DijkstraMap;
+use game::distance;
+use grid::Coord;
+use object::{Awareness,Behavior,Creature};
+
+/// Names of the dijkstra maps a monster can desire
+pub const DESIRES: [&str;6] = [
+    "player",   //the player's position
+    "unseen",   //tiles the player cannot see
+    "items",    //gold lying on the floor
+    "allies",   //other creatures of the same kind
+    "exits",    //doors and stairs
+    "safety",   //tiles far from the player, avoiding dead ends
+];
+
+/// How far a pack animal may stray from the rest of its pack before it stops hunting to regroup
+pub const PACK_RANGE: usize = 6;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub enum Mood {
+    Hunt,       //follow the creature's usual desires
+    Flee,       //follow the creature's flee desires
+    Regroup,    //head back to the pack
+    Idle,       //follow the creature's idle desires, not having noticed the player
+    Search,     //head for where the player was last seen or heard
+}
+
+/// The maps creatures can desire. They are gathered once each time the game runs the creatures' turns, and
+/// only those that some creature on the level may desire are there.
+pub struct DesireMaps {
+    maps: HashMap<&'static str,DijkstraMap>,    //by name, for every name in DESIRES but "allies"
+    allies: HashMap<String,DijkstraMap>,        //for each kind of creature, a map of where its kind are
+    // Sums already worked out, by kind of creature, mood and place headed for. Creatures of one kind usually
+    // share their desires, so they can share the sum as well; the desires are kept to check.
+    combined: HashMap<(String,Mood,Option<Coord>),Combined>,
+}
+
+// A sum of maps, and the desires it was made from
+type Combined = (Vec<(String,f64)>,Option<DijkstraMap>);
+
+impl DesireMaps {
+    pub fn new(maps: HashMap<&'static str,DijkstraMap>, allies: HashMap<String,DijkstraMap>) -> DesireMaps {
+        DesireMaps { maps: maps, allies: allies, combined: HashMap::new() }
+    }
+
+    /// Whether the map with the given name is there, because some creature may desire it
+    pub fn has(&self, name: &str) -> bool { self.maps.contains_key(name) }
+
+    /// Replace the map of items, for when gold is picked up
+    pub fn set_items(&mut self, items: DijkstraMap) {
+        self.maps.insert("items", items);
+        self.combined.clear();
+    }
+
+    /// The map with the given name, as seen by a creature of the given kind
+    pub fn get(&self, name: &str, kind: &str) -> Option<&DijkstraMap> {
+        match name {
+            "allies" => self.allies.get(kind),
+            _ => self.maps.get(name),
+        }
+    }
+
+    /// The sum of the maps a creature of the given kind desires in the given mood, plus the map towards the
+    /// place it is headed for if it has one. The sum from an earlier creature of the same kind is reused if
+    /// it had the same desires and was headed for the same place, so creatures do not each pay for a sum of
+    /// their own.
+    pub fn combined(&mut self, desires: &[(String,f64)], kind: &str, mood: Mood, towards: Option<(Coord,&DijkstraMap)>) -> Option<&DijkstraMap> {
+        let key = (kind.to_string(), mood, towards.map(|(goal,_)| goal));
+        let known = match self.combined.get(&key) {
+            Some((known, _)) => &known[..] == desires,
+            None => false,
+        };
+        if !known {
+            let map = match (self.combine(desires, kind), towards) {
+                (Some(map), Some((_, towards))) => Some(map + towards),
+                (None, Some((_, towards))) => Some(towards.clone()),
+                (map, None) => map,
+            };
+            self.combined.insert(key.clone(), (desires.to_vec(), map));
+        }
+        self.combined[&key].1.as_ref()
+    }
+
+    /// The sum of the desired maps, each multiplied by its weight. Maps with nothing in them (such as items
+    /// when there are none left) are skipped. None if there is nothing to move towards.
+    pub fn combine(&self, desires: &[(String,f64)], kind: &str) -> Option<DijkstraMap> {
+        let mut total: Option<DijkstraMap> = None;
+        for &(ref name, weight) in desires.iter() {
+            if let Some(map) = self.get(name, kind) {
+                if map.is_empty() { continue }
+                let weighted = map * weight;
+                total = Some(match total {
+                    Some(total) => total + weighted,
+                    None => weighted,
+                });
+            }
+        }
+        total
+    }
+}
+
+/// Decide the mood of a creature, given the positions of the other creatures of its kind
+pub fn mood(creature: &Creature, allies: &[Coord]) -> Mood {
+    let position = creature.coordinates();
+    let wounded = (creature.hp as f64) < creature.flee_below * creature.max_hp as f64;
+    // Thieves make off with whatever they have taken
+    let loaded = creature.ai == Behavior::Thief && creature.gold > 0;
+    if creature.fear > 0 || wounded || loaded { return Mood::Flee }
+    // Only a creature that knows where the player is can hunt them
+    match creature.awareness {
+        Awareness::Unaware => return Mood::Idle,
+        Awareness::Searching(_) => return Mood::Search,
+        Awareness::Aware => (),
+    }
+    // A pack animal that has strayed from the rest of its pack goes back to them before hunting
+    let strayed = !allies.is_empty() && allies.iter().all(|&ally| distance(position, ally) > PACK_RANGE);
+    if creature.ai == Behavior::Pack && strayed { return Mood::Regroup }
+    Mood::Hunt
+}
+
+/// The names of the maps a creature may desire in any mood
+pub fn desired(creature: &Creature) -> Vec<&str> {
+    let mut output: Vec<&str> = creature.desires.iter().chain(creature.flee.iter()).chain(creature.idle.iter()).map(|(name,_)| &name[..]).collect();
+    if creature.ai == Behavior::Pack { output.push("allies") }
+    output
+}
+
+/// The desires a creature follows in the given mood. Searching creatures follow none: they head for a
+/// place rather than a kind of place.
+pub fn desires(creature: &Creature, mood: Mood) -> Vec<(String,f64)> {
+    match mood {
+        Mood::Hunt => creature.desires.clone(),
+        Mood::Flee => creature.flee.clone(),
+        Mood::Regroup => vec![("allies".to_string(), 1.0)],
+        Mood::Idle => creature.idle.clone(),
+        Mood::Search => vec![],
+    }
+}
@@ -0,0 +1,145 @@
+//! Keys and menus shared by all front-ends. A front-end translates its raw input into `Key`s and passes them
+//! to `Controls`, which keeps track of whether the player is choosing a spell, aiming one or choosing a door,
+//! and turns the keys into game commands.
+
+use game::{Command,Game};
+use grid::Coord;
+use spell::Targeting;
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Key {
+    Direction(isize,isize), //Arrow keys
+    Number(u32),            //Number keys. They move the player like a numpad, or pick from menus.
+    Automove,
+    Descend,
+    Ascend,
+    Cast,
+    Open,
+    Close,
+    NextTarget,
+    Confirm,
+    Cancel,
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Mode {
+    Play,
+    ChooseSpell,
+    Target(usize,Coord),    //Aiming the n-th spell of the spellbook. Holds the position of the cursor.
+    Open,                   //Choosing the direction of a door to open
+    Close,                  //Choosing the direction of a door to close
+}
+
+pub struct Controls { pub mode: Mode }
+
+// Direction of each number key, laid out as on a numpad
+fn numpad(n: u32) -> Option<(isize,isize)> {
+    match n {
+        1 => Some((-1,1)),
+        2 => Some((0,1)),
+        3 => Some((1,1)),
+        4 => Some((-1,0)),
+        5 => Some((0,0)),
+        6 => Some((1,0)),
+        7 => Some((-1,-1)),
+        8 => Some((0,-1)),
+        9 => Some((1,-1)),
+        _ => None,
+    }
+}
+
+impl Default for Controls {
+    fn default() -> Controls { Controls::new() }
+}
+
+impl Controls {
+    pub fn new() -> Controls { Controls { mode: Mode::Play } }
+
+    /// Where the targeting cursor is, if the player is aiming a spell
+    pub fn cursor(&self) -> Option<Coord> {
+        match self.mode {
+            Mode::Target(_,c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Handle a key press. Returns the command for the game to carry out, which is `Command::None` while the
+    /// player is still in a menu.
+    pub fn press(&mut self, game: &Game, key: Key) -> Command {
+        let key = match key {
+            Key::Number(n) if self.mode != Mode::ChooseSpell => match numpad(n) {
+                Some((i,j)) => Key::Direction(i,j),
+                None => return Command::None,
+            },
+            _ => key,
+        };
+        match (self.mode, key) {
+            (Mode::Play, Key::Direction(i,j)) => Command::Move(i,j),
+            (Mode::Play, Key::Automove) => Command::Automove,
+            (Mode::Play, Key::Descend) => Command::Descend,
+            (Mode::Play, Key::Ascend) => Command::Ascend,
+            (Mode::Play, Key::Cast) => {
+                self.mode = Mode::ChooseSpell;
+                Command::None
+            }
+            (Mode::Play, Key::Open) => {
+                self.mode = Mode::Open;
+                Command::None
+            }
+            (Mode::Play, Key::Close) => {
+                self.mode = Mode::Close;
+                Command::None
+            }
+            (Mode::Open, Key::Direction(i,j)) => {
+                self.mode = Mode::Play;
+                Command::Open(i,j)
+            }
+            (Mode::Close, Key::Direction(i,j)) => {
+                self.mode = Mode::Play;
+                Command::Close(i,j)
+            }
+            (Mode::ChooseSpell, Key::Number(n)) if n >= 1 => {
+                let n = n as usize - 1;
+                let origin = game.player().coordinates();
+                let targeting = game.player().spells.get(n).and_then(|name| game.spell(name)).map(|spell| spell.target);
+                match targeting {
+                    // Spells on the caster need no aiming
+                    Some(Targeting::Caster) => {
+                        self.mode = Mode::Play;
+                        Command::Cast(n,origin)
+                    }
+                    // Start aiming at the nearest creature, if there is one
+                    Some(_) => {
+                        let start = game.visible_creatures().first().cloned().unwrap_or(origin);
+                        self.mode = Mode::Target(n,start);
+                        Command::None
+                    }
+                    None => Command::None,
+                }
+            }
+            (Mode::Target(n,c), Key::Direction(i,j)) => {
+                if let Some(c) = game.map().grid.offset(c,i,j) { self.mode = Mode::Target(n,c) }
+                Command::None
+            }
+            (Mode::Target(n,c), Key::NextTarget) => {
+                // Move the cursor to the next creature in order of distance
+                let targets = game.visible_creatures();
+                let next = match targets.iter().position(|&t| t == c) {
+                    Some(k) => targets[(k+1) % targets.len()],
+                    None => targets.first().cloned().unwrap_or(c),
+                };
+                self.mode = Mode::Target(n,next);
+                Command::None
+            }
+            (Mode::Target(n,c), Key::Confirm) => {
+                self.mode = Mode::Play;
+                Command::Cast(n,c)
+            }
+            (_, Key::Cancel) => {
+                self.mode = Mode::Play;
+                Command::None
+            }
+            _ => Command::None,
+        }
+    }
+}
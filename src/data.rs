@@ -0,0 +1,181 @@
+//! Parser for the game's data files.
+//!
+//! A data file is a list of entries. Each entry starts with its name in square brackets, followed by
+//! `key = value` lines. Keys may be repeated within an entry. Blank lines and lines starting with `#` are
+//! ignored. Errors point at the file, line and entry they were found in, so that whoever edits the data
+//! can find the mistake.
+
+use std::fmt;
+use std::str::FromStr;
+
+pub struct Field {
+    pub key: String,
+    pub value: String,
+    pub line: usize,
+}
+
+pub struct Entry {
+    pub file: String,
+    pub name: String,
+    pub line: usize,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug)]
+pub struct DataError {
+    pub file: String,
+    pub line: usize,
+    pub entry: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.entry {
+            Some(ref entry) => write!(f, "{}:{}: in [{}]: {}", self.file, self.line, entry, self.message),
+            None => write!(f, "{}:{}: {}", self.file, self.line, self.message),
+        }
+    }
+}
+
+/// Split a data file into entries
+pub fn parse(file: &str, text: &str) -> Result<Vec<Entry>,DataError> {
+    let mut entries: Vec<Entry> = vec![];
+    for (n,line) in text.lines().enumerate() {
+        let line = line.trim();
+        let number = n+1;
+        if line.is_empty() || line.starts_with('#') { continue }
+        if line.starts_with('[') && line.ends_with(']') {
+            let name = line[1..line.len()-1].trim().to_string();
+            if entries.iter().any(|entry| entry.name == name) {
+                return Err(DataError { file: file.to_string(), line: number, entry: Some(name), message: "duplicate entry".to_string() });
+            }
+            entries.push(Entry { file: file.to_string(), name: name, line: number, fields: vec![] });
+            continue;
+        }
+        let entry = match entries.last_mut() {
+            Some(entry) => entry,
+            None => return Err(DataError { file: file.to_string(), line: number, entry: None, message: "expected an entry name in square brackets".to_string() }),
+        };
+        match line.find('=') {
+            Some(split) => entry.fields.push(Field {
+                key: line[..split].trim().to_string(),
+                value: line[split+1..].trim().to_string(),
+                line: number,
+            }),
+            None => return Err(entry.error(number, "expected 'key = value'")),
+        }
+    }
+    Ok(entries)
+}
+
+impl Entry {
+    /// An error about this entry, found at the given line
+    pub fn error(&self, line: usize, message: &str) -> DataError {
+        DataError { file: self.file.clone(), line: line, entry: Some(self.name.clone()), message: message.to_string() }
+    }
+
+    /// Complain about any key that is not in the given list, to catch typos
+    pub fn check_keys(&self, keys: &[&str]) -> Result<(),DataError> {
+        match self.fields.iter().find(|field| !keys.contains(&&field.key[..])) {
+            Some(field) => Err(self.error(field.line, &format!("unknown key '{}'", field.key))),
+            None => Ok(()),
+        }
+    }
+
+    /// All the fields with the given key, in order
+    pub fn all(&self, key: &str) -> Vec<&Field> {
+        self.fields.iter().filter(|field| field.key == key).collect()
+    }
+
+    /// The field with the given key. It is an error for the key to be missing or repeated.
+    pub fn field(&self, key: &str) -> Result<&Field,DataError> {
+        let fields = self.all(key);
+        match fields.len() {
+            0 => Err(self.error(self.line, &format!("missing '{}'", key))),
+            1 => Ok(fields[0]),
+            _ => Err(self.error(fields[1].line, &format!("'{}' is given more than once", key))),
+        }
+    }
+
+    /// The field with the given key, if it is present
+    pub fn optional(&self, key: &str) -> Result<Option<&Field>,DataError> {
+        match self.all(key).len() {
+            0 => Ok(None),
+            _ => self.field(key).map(Some),
+        }
+    }
+
+    /// Parse the value of a field as a number (or anything else that can be parsed)
+    pub fn parse<T: FromStr>(&self, field: &Field) -> Result<T,DataError> {
+        field.value.parse().map_err(|_| self.error(field.line, &format!("'{}' is not a valid value for '{}'", field.value, field.key)))
+    }
+
+    /// Parse a required field
+    pub fn value<T: FromStr>(&self, key: &str) -> Result<T,DataError> {
+        let field = self.field(key)?;
+        self.parse(field)
+    }
+
+    /// Parse an optional field, using the default if it is missing
+    pub fn value_or<T: FromStr>(&self, key: &str, default: T) -> Result<T,DataError> {
+        match self.optional(key)? {
+            Some(field) => self.parse(field),
+            None => Ok(default),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> (usize,Option<String>,String) {
+        let e = match parse("test.txt", text) {
+            Ok(entries) => entries[0].check_keys(&["cost"]).and_then(|_| entries[0].field("cost").map(|_| ())).unwrap_err(),
+            Err(e) => e,
+        };
+        (e.line, e.entry, e.message)
+    }
+
+    #[test]
+    fn entries_and_fields() {
+        let entries = parse("test.txt", "# a comment\n\n[bolt]\ncost = 3\n[blink]\ncost=4\n").unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((&entries[1].name[..], entries[1].line), ("blink", 5));
+        assert_eq!(entries[1].value::<i32>("cost").unwrap(), 4);
+    }
+
+    #[test]
+    fn unknown_key() {
+        assert_eq!(error("[bolt]\ncost = 3\ncots = 3\n"), (3, Some("bolt".to_string()), "unknown key 'cots'".to_string()));
+    }
+
+    #[test]
+    fn duplicate_entry() {
+        assert_eq!(error("[bolt]\ncost = 3\n[bolt]\ncost = 3\n"), (3, Some("bolt".to_string()), "duplicate entry".to_string()));
+    }
+
+    #[test]
+    fn missing_key() {
+        assert_eq!(error("[bolt]\n"), (1, Some("bolt".to_string()), "missing 'cost'".to_string()));
+    }
+
+    #[test]
+    fn repeated_key() {
+        assert_eq!(error("[bolt]\ncost = 3\ncost = 4\n"), (3, Some("bolt".to_string()), "'cost' is given more than once".to_string()));
+    }
+
+    #[test]
+    fn malformed_lines() {
+        assert_eq!(error("cost = 3\n"), (1, None, "expected an entry name in square brackets".to_string()));
+        assert_eq!(error("[bolt]\ncost 3\n"), (2, Some("bolt".to_string()), "expected 'key = value'".to_string()));
+    }
+
+    #[test]
+    fn bad_value() {
+        let entries = parse("test.txt", "[bolt]\ncost = lots\n").unwrap();
+        let e = entries[0].value::<i32>("cost").unwrap_err();
+        assert_eq!(e.to_string(), "test.txt:2: in [bolt]: 'lots' is not a valid value for 'cost'");
+    }
+}
@@ -1,13 +1,24 @@
 /*! Dijkstra algorithm
 
- As input, accepts a vector of rows, where each row contains one of three values:
+ As input, accepts a grid where each tile holds one of these values:
  - Impassable
  - Passable
  - Goal
+ - Cost(f64), a passable tile that costs the given (positive) amount to step onto. Passable is Cost(1.0).
+ - Seed(f64), a goal whose distance starts at the given value instead of 0. Goal is the same as Seed(0.0).
+   Lower seeds pull harder, so a goal worth more should be given a lower (possibly negative) seed.
 
- As output, it returns a dijkstra map object, where each row is one of two values:
+ As output, it returns a dijkstra map object, where each tile is one of two values:
  - Impassable
- - Value(int), where int is the number of steps from a goal
+ - Value(f64), where f64 is the cost of the cheapest path from a goal, plus that goal's seed
+
+ A map built this way can be kept up to date as goals come and go or the terrain changes, with `add_goal`,
+ `remove_goal` and `set_cost`. These only revisit the tiles whose values could have changed, and give the
+ same result as building the map again. That pays off when few values change, such as when a door opens or
+ closes. Moving the only goal of a map changes nearly every value, and repairing them one at a time is
+ slower than building the map again with a breadth first search, so maps whose goals move should be
+ rebuilt. Maps made with the arithmetic operators, `rescan` or `flee` no longer correspond to a set of goals,
+ so they should be rebuilt instead.
 
 */
 
@@ -16,6 +27,33 @@ pub enum DijkstraTile {
 	Passable,
 	Goal,
 	Value(f64),
+	Cost(f64),
+	Seed(f64),
+}
+
+impl DijkstraTile {
+	/// The cost of stepping onto the tile, or None if it cannot be entered
+	fn cost(&self) -> Option<f64> {
+		match *self {
+			DijkstraTile::Impassable => None,
+			DijkstraTile::Cost(c) => Some(c),
+			_ => Some(1.0),
+		}
+	}
+
+	/// The value the tile starts with, if it is a goal
+	fn seed(&self) -> Option<f64> {
+		match *self {
+			DijkstraTile::Goal => Some(0.0),
+			DijkstraTile::Seed(v) => Some(v),
+			_ => None,
+		}
+	}
+
+	/// Whether the tile only uses unit costs and zero seeds
+	fn is_simple(&self) -> bool {
+		!matches!(*self, DijkstraTile::Cost(_) | DijkstraTile::Seed(_))
+	}
 }
 
 // We will need to duplicate tiles, so we will implement clone here.
@@ -26,111 +64,343 @@ impl Clone for DijkstraTile {
 			DijkstraTile::Passable => DijkstraTile::Passable,
 			DijkstraTile::Goal => DijkstraTile::Goal,
 			DijkstraTile::Value(n) => DijkstraTile::Value(n),
+			DijkstraTile::Cost(n) => DijkstraTile::Cost(n),
+			DijkstraTile::Seed(n) => DijkstraTile::Seed(n),
 		}
 	}
 }
 
-pub struct DijkstraMap { map: Vec<Vec<DijkstraTile>> }
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap,HashSet,VecDeque};
+use grid::{Coord,Grid};
+
+/// How strongly fleeing creatures are pushed away from a goal. Values below -1 make routes that lead past the
+/// goal to open space worth more than dead ends just out of reach.
+pub const FLEE_FACTOR: f64 = -1.2;
+
+/// How much a tile held by another creature adds to a route through it. Creatures are not walls: they move
+/// on, so a creature takes a way around one only if it is shorter than waiting for the way to clear.
+pub const CROWD_COST: f64 = 6.0;
+
+/// How far from a blocked creature the search for a way around reaches. Going to the edge of this area and
+/// back costs more than CROWD_COST, so a way around never leads back through the blocked tile.
+pub const DETOUR_RANGE: usize = 6;
+
+pub struct DijkstraMap {
+	values: Grid<Option<f64>>,	// None for tiles that cannot be reached
+	costs: Grid<Option<f64>>,	// the cost of stepping onto each tile, kept so that the map can be rescanned
+	seeds: Grid<Option<f64>>,	// the value each goal starts with, kept so that the map can be updated
+}
 
 impl Clone for DijkstraMap {
 	fn clone(&self) -> DijkstraMap {
-		DijkstraMap { map: self.map.clone()}
+		DijkstraMap { values: self.values.clone(), costs: self.costs.clone(), seeds: self.seeds.clone() }
+	}
+}
+
+/// An entry in the frontier of the weighted algorithm. Ordered so that the binary heap pops the lowest value first.
+struct Frontier { value: f64, index: usize }
+
+impl PartialEq for Frontier {
+	fn eq(&self, other: &Frontier) -> bool { self.value == other.value }
+}
+impl Eq for Frontier {}
+impl PartialOrd for Frontier {
+	fn partial_cmp(&self, other: &Frontier) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+impl Ord for Frontier {
+	fn cmp(&self, other: &Frontier) -> Ordering {
+		other.value.partial_cmp(&self.value).unwrap_or(Ordering::Equal)
+	}
+}
+
+/// Spread values outward from the tiles that already have one, where `costs` holds the cost of stepping
+/// onto each tile (None if it cannot be entered). Tiles are settled cheapest first using a binary heap,
+/// so each tile is finalised once.
+fn propagate(costs: &Grid<Option<f64>>, mut values: Grid<Option<f64>>) -> Grid<Option<f64>> {
+	let mut frontier = BinaryHeap::new();
+	for (index,value) in values.iter().enumerate() {
+		if let Some(v) = *value { frontier.push(Frontier { value: v, index: index }) }
 	}
+	settle(costs, &mut values, frontier);
+	values
+}
+
+/// Spread values outward from the tiles in the frontier, cheapest first. Values only ever get lower.
+fn settle(costs: &Grid<Option<f64>>, values: &mut Grid<Option<f64>>, mut frontier: BinaryHeap<Frontier>) {
+	while let Some(Frontier { value, index }) = frontier.pop() {
+		let c = values.coord(index);
+		// Skip entries that were superseded by a cheaper path after being queued
+		if values[c].is_some_and(|v| value > v) { continue }
+		for next in values.neighbours(c) {
+			if let Some(cost) = costs[next] {
+				let v = value + cost;
+				if values[next].is_none_or(|old| v < old) {
+					values[next] = Some(v);
+					frontier.push(Frontier { value: v, index: values.index(next) });
+				}
+			}
+		}
+	}
+}
+
+/// Whether stepping onto a tile that costs `cost` from a tile worth `from` gives exactly `to`, allowing for
+/// rounding. Such a step may be the one the tile got its value from.
+fn is_tight(from: f64, cost: f64, to: f64) -> bool {
+	(from + cost - to).abs() <= 1e-9 * to.abs().max(1.0)
 }
 
 impl DijkstraMap {
 	//constructor function for a brand new map
-	pub fn new(map: &Vec<Vec<DijkstraTile>>) -> DijkstraMap {
-		// Create an output map filled with impassable tiles
-		let mut output = vec![vec![DijkstraTile::Impassable; map[0].len()];map.len()];
-
-		// First, create 3 boxes
-		let mut current_nodes = vec![];
-		let mut next_nodes = vec![];
-		let mut unvisited = vec![];
-		//Fill the current_nodes box with goals and the unvisited box with passables
-		for row_index in 0..map.len() {
-			for column_index in 0..map[row_index].len() {
-				match map[row_index][column_index] {
-					DijkstraTile::Goal => current_nodes.push((column_index,row_index)),
-					DijkstraTile::Passable => unvisited.push((column_index,row_index)),
-					DijkstraTile::Value(_) => unvisited.push((column_index,row_index)),
-					_ => (),
+	pub fn new(map: &Grid<DijkstraTile>) -> DijkstraMap {
+		let costs = map.map(|tile| tile.cost());
+		let seeds = map.map(|tile| tile.seed());
+
+		let values = match map.iter().all(|tile| tile.is_simple()) {
+			// With unit costs and zero seeds, a plain breadth first search visits tiles in order of
+			// distance, so every tile is reached once and the whole map takes linear time
+			true => {
+				let mut values = seeds;
+				let mut queue: VecDeque<Coord> = values.coords().filter(|&c| values[c].is_some()).collect();
+				while let Some(c) = queue.pop_front() {
+					let distance = values[c].unwrap_or(0.0) + 1.0;
+					for next in values.neighbours(c) {
+						if costs[next].is_some() && values[next].is_none() {
+							values[next] = Some(distance);
+							queue.push_back(next);
+						}
+					}
 				}
+				values
 			}
+			// Otherwise, settle tiles cheapest first
+			false => propagate(&costs, seeds),
+		};
+
+		DijkstraMap { values: values, costs: costs, seeds: map.map(|tile| tile.seed()) }
+	}
+
+	/// A map built from the cost of stepping onto each tile, None where it cannot be entered, and the value
+	/// each goal starts with. Goals cost 1 to step onto, whatever their cost says.
+	pub fn from_costs(costs: &Grid<Option<f64>>, seeds: &Grid<Option<f64>>) -> DijkstraMap {
+		DijkstraMap::new(&Grid::from_fn(costs.width(), costs.height(), |c| match (costs[c], seeds[c]) {
+			(None, _) => DijkstraTile::Impassable,
+			(Some(_), Some(0.0)) => DijkstraTile::Goal,
+			(Some(_), Some(s)) => DijkstraTile::Seed(s),
+			(Some(1.0), None) => DijkstraTile::Passable,
+			(Some(c), None) => DijkstraTile::Cost(c),
+		}))
+	}
+
+	/// Make the tile at c a goal
+	pub fn add_goal(&mut self, c: Coord) { self.add_seed(c, 0.0) }
+
+	/// Make the tile at c a goal that starts with the given value, replacing any goal already there
+	pub fn add_seed(&mut self, c: Coord, seed: f64) {
+		if !self.values.contains(c) { return }
+		let old = self.seeds[c];
+		self.seeds[c] = Some(seed);
+		match old {
+			Some(old) if seed > old => self.repair_increase(c),
+			_ => self.repair_decrease(c),
 		}
-		// Until we run out of current nodes, use dijkstra's algorithm to label map current_nodes
-		let mut distance = 0isize;
-		while current_nodes.len() > 0 {
-			// First, iterate over current nodes
-			for (i,j) in current_nodes {
-				//Set the corresponding output to a tile with value distance
-				output[j][i] = DijkstraTile::Value(distance as f64);
-				// Then move all adjacent nodes from unvisited to next_nodes
-				for (i2,j2) in unvisited.clone() { if (i as isize - i2 as isize).abs() <= 1 && (j as isize - j2 as isize ).abs() <= 1 { next_nodes.push((i2,j2)) } };
-				unvisited.retain(|&(i2,j2)|(i as isize -i2 as isize ).abs() > 1 || (j as isize - j2 as isize ).abs() > 1);
+	}
+
+	/// Stop the tile at c from being a goal
+	pub fn remove_goal(&mut self, c: Coord) {
+		if !self.values.contains(c) || self.seeds[c].is_none() { return }
+		self.seeds[c] = None;
+		self.repair_increase(c);
+	}
+
+	/// Change the cost of stepping onto the tile at c. None makes it impassable, for example when a door
+	/// closes. Goals built with `new` cost 1 to step onto, and keep whatever cost is set here.
+	pub fn set_cost(&mut self, c: Coord, cost: Option<f64>) {
+		if !self.values.contains(c) { return }
+		let old = self.costs[c];
+		self.costs[c] = cost;
+		match (old, cost) {
+			(Some(old), Some(new)) if new == old => (),
+			(Some(old), Some(new)) if new < old => self.repair_decrease(c),
+			(None, Some(_)) => self.repair_decrease(c),
+			(Some(_), _) => self.repair_increase(c),
+			(None, None) => (),
+		}
+	}
+
+	/// Update the map after the tile at c may have become cheaper to reach. Only tiles that the lower value
+	/// reaches are visited.
+	fn repair_decrease(&mut self, c: Coord) {
+		let cost = match self.costs[c] {
+			Some(cost) => cost,
+			None => return,
+		};
+		// The best of the tile's own seed and a step from any of its neighbours
+		let mut best = self.seeds[c];
+		for n in self.values.neighbours(c) {
+			if let Some(v) = self.value(n) {
+				if best.is_none_or(|b| v + cost < b) { best = Some(v + cost) }
+			}
+		}
+		let best = match (best, self.value(c)) {
+			(Some(b), Some(v)) if b < v => b,
+			(Some(b), None) => b,
+			_ => return,
+		};
+		self.values[c] = Some(best);
+		let mut frontier = BinaryHeap::new();
+		frontier.push(Frontier { value: best, index: self.values.index(c) });
+		settle(&self.costs, &mut self.values, frontier);
+	}
+
+	/// Update the map after the tile at c may have become more expensive to reach. Every tile whose value
+	/// could have come through c forgets it, and values flow back in from the tiles around them.
+	fn repair_increase(&mut self, c: Coord) {
+		let values = &mut self.values;
+		// Find the tiles whose value may have come through c: c itself, and every tile reached from it by
+		// steps that add up exactly to the tile's value.
+		let mut affected = HashSet::new();
+		affected.insert(values.index(c));
+		let mut stack = vec![c];
+		while let Some(a) = stack.pop() {
+			let from = match values[a] {
+				Some(v) => v,
+				None => continue,
+			};
+			for n in values.neighbours(a) {
+				if let (Some(to), Some(cost)) = (values[n], self.costs[n]) {
+					if is_tight(from, cost, to) && affected.insert(values.index(n)) { stack.push(n) }
+				}
 			}
-			// Next, move everything from next_nodes into current_nodes
-			current_nodes = next_nodes.clone();
-			next_nodes = vec![];
-			// Finally, increment distance
-			distance += 1;
 		}
 
-		// Print dijkstra map for debug
-		/*
-		for row in output.iter() {
-			println!("");
-			for tile in row.iter() {
-				match tile {
-					&DijkstraTile::Impassable => print!("#"),
-					&DijkstraTile::Value(x) if x < 10 => print!("{}",x),
-					&DijkstraTile::Value(x) => print!("*"),
-					_ => print!("x"),
+		// Forget their values, keeping only those of goals
+		for &index in affected.iter() {
+			let a = values.coord(index);
+			values[a] = match self.costs[a] {
+				Some(_) => self.seeds[a],
+				None => None,
+			};
+		}
+
+		// Let values flow back in from the goals among them and from the tiles around them
+		let mut frontier = BinaryHeap::new();
+		for &index in affected.iter() {
+			let a = values.coord(index);
+			let cost = match self.costs[a] {
+				Some(cost) => cost,
+				None => continue,
+			};
+			for n in values.neighbours(a) {
+				if affected.contains(&values.index(n)) { continue }
+				if let Some(v) = values[n] {
+					if values[a].is_none_or(|old| v + cost < old) { values[a] = Some(v + cost) }
 				}
 			}
+			if let Some(v) = values[a] { frontier.push(Frontier { value: v, index: index }) }
 		}
-		println!("");
-		*/
+		settle(&self.costs, values, frontier);
+	}
 
-		// Return a dijkstra map
-		DijkstraMap { map: output}
+	/// Treat every value on the map as a goal seeded with that value, and let values spread again. Tiles
+	/// only ever get lower, to the cheapest of their own value and a path from a lower tile.
+	pub fn rescan(&self) -> DijkstraMap {
+		DijkstraMap { values: propagate(&self.costs, self.values.clone()), costs: self.costs.clone(), seeds: self.values.clone() }
 	}
 
-	/// Create a dijkstra map from an existing one by marking given coordinates as impassable
-	/// Makes it much faster to create new maps from existing ones
-	pub fn with_obstacles(&self, coordinates: Vec<(usize,usize)>) -> DijkstraMap {
-		let mut new_map = self.clone();
-		for (i,j) in coordinates { new_map.map[j][i] = DijkstraTile::Impassable }
-		new_map
+	/// Create a map for running away from this map's goals. Simply negating the map leads creatures into
+	/// the far corners of dead ends; scaling by FLEE_FACTOR and rescanning makes them route around the goal
+	/// towards open areas instead.
+	pub fn flee(&self) -> DijkstraMap {
+		(self * FLEE_FACTOR).rescan()
+	}
+
+	/// The value of the tile at the given coordinates, or None if it is impassable or outside the map
+	pub fn value(&self, c: Coord) -> Option<f64> {
+		match self.values.get(c) {
+			Some(&Some(v)) => Some(v),
+			_ => None,
+		}
+	}
+
+	/// Whether no tile has a value, as happens when there are no goals
+	pub fn is_empty(&self) -> bool {
+		self.values.iter().all(|value| value.is_none())
+	}
+
+	/// Where a creature at `from` should step next when the tiles in `occupied` are held by other creatures.
+	/// If the best step is free it is taken. Otherwise the tiles within DETOUR_RANGE are searched again,
+	/// with occupied tiles costing CROWD_COST more to pass through, and the creature takes the first step
+	/// of the cheapest route out of that area. If that step is occupied too, it waits where it is.
+	pub fn get_next_step_around(&self, from: Coord, occupied: &HashSet<Coord>) -> Coord {
+		let step = self.get_next_step(from);
+		if step == from || !occupied.contains(&step) { return step }
+
+		// Search outward from the creature. Routes leave the area through its edge or end at a goal, so those
+		// tiles are scored by the cost of getting there plus their value, and remember the first step taken
+		// towards them. Other tiles may owe their value to a route through the blocked tile.
+		// The area is small, so what was reached is kept in a square around the creature rather than a map
+		const SIDE: usize = 2 * DETOUR_RANGE + 1;
+		let local = |c: Coord| (c.1 + DETOUR_RANGE - from.1) * SIDE + c.0 + DETOUR_RANGE - from.0;
+		let mut reached: [Option<(f64,Coord)>; SIDE * SIDE] = [None; SIDE * SIDE];
+		let mut frontier = BinaryHeap::new();
+		let mut best = (f64::INFINITY, from);
+		reached[local(from)] = Some((0.0, from));
+		frontier.push(Frontier { value: 0.0, index: self.values.index(from) });
+		while let Some(Frontier { value, index }) = frontier.pop() {
+			let c = self.values.coord(index);
+			let (cost, first) = reached[local(c)].unwrap();
+			if value > cost { continue }
+			let edge = c.0.abs_diff(from.0).max(c.1.abs_diff(from.1)) == DETOUR_RANGE;
+			if let Some(v) = self.value(c) {
+				if c != from && (edge || self.is_lowest(c)) && cost + v < best.0 { best = (cost + v, first) }
+			}
+			for next in self.values.neighbours(c) {
+				let far = next.0.abs_diff(from.0).max(next.1.abs_diff(from.1)) > DETOUR_RANGE;
+				let step_cost = match (self.costs[next], self.values[next]) {
+					(Some(step_cost), Some(_)) if !far => step_cost,
+					_ => continue,
+				};
+				let crowd = match occupied.contains(&next) {
+					true => CROWD_COST,
+					false => 0.0,
+				};
+				let total = cost + step_cost + crowd;
+				if reached[local(next)].is_none_or(|(old,_)| total < old) {
+					reached[local(next)] = Some((total, if c == from { next } else { first }));
+					frontier.push(Frontier { value: total, index: self.values.index(next) });
+				}
+			}
+		}
+
+		match occupied.contains(&best.1) {
+			true => from,
+			false => best.1,
+		}
+	}
+
+	/// Whether no neighbour of the tile has a lower value, as for a goal
+	fn is_lowest(&self, c: Coord) -> bool {
+		match self.value(c) {
+			Some(v) => self.values.neighbours(c).all(|n| self.value(n).is_none_or(|m| m >= v)),
+			None => false,
+		}
 	}
 
 	// Function to determine where an object should next step on the map
-	pub fn get_next_step(&self, coordinates:(usize,usize)) -> (usize,usize) {
+	pub fn get_next_step(&self, coordinates: Coord) -> Coord {
 		// by default, next step is to do nothing
 		let mut next_step = coordinates;
-		let mut best_tile = match coordinates { (i,j) => self.map[j][i].clone() };
-
-		// Create a list of adjacent coordinates. We order them so that motion in straight lines is preferred.
-		let mut candidates: Vec<(isize,isize)> = vec![(-1,0),(0,1),(1,0),(0,-1),(-1,1),(1,1),(1,-1),(-1,-1)];
-		candidates = match coordinates { (i0, j0) => candidates.iter().map(|&(i,j)| (i + i0 as isize, j + j0 as isize)).collect() };
-
-		//match coordinates { (x0,y0) => for x in -1isize..2isize { for y in -1isize..2isize { candidates.push((x+x0 as isize,y+y0 as isize)) } } };
-
-		// Get rid of coordinates that fall outside the dimensions of the map
-		candidates.retain(|&(i,j)| i >= 0 && i < self.map[0].len() as isize && j >= 0 && j < self.map.len() as isize );
-
-		// Look through the tiles. If one of them is better than the current candidate, make the replacement
-		for (i,j) in candidates {
-			match self.map[j as usize][i as usize] {
-				DijkstraTile::Value(m) => {
-					match best_tile {
-						DijkstraTile::Value(n) if n <= m => (),
-						_ => {
-							next_step = (i as usize, j as usize );
-							best_tile = DijkstraTile::Value(m);
-						},
-					}
+		let mut best = self.value(coordinates);
+
+		// Look through the adjacent tiles. If one of them is better than the current candidate, make the
+		// replacement. Neighbours come in an order that prefers motion in straight lines.
+		for c in self.values.neighbours(coordinates) {
+			match (self.value(c), best) {
+				(Some(m), Some(n)) if n <= m => (),
+				(Some(m), _) => {
+					next_step = c;
+					best = Some(m);
 				},
 				_ => (),
 			}
@@ -138,48 +408,49 @@ impl DijkstraMap {
 		// Return the best coordinates
 		next_step
 	}
+
+	// Multiply every value by a factor, for the operators below
+	fn scale(&mut self, factor: f64) {
+		for value in self.values.iter_mut() {
+			if let Some(n) = *value { *value = Some(n*factor) }
+		}
+	}
+
+	// Add another map's values to this one, for the operators below. Tiles without a value in the other map
+	// are left alone.
+	fn add_map(&mut self, rhs: &DijkstraMap) {
+		for (value,other) in self.values.iter_mut().zip(rhs.values.iter()) {
+			if let (Some(n), &Some(m)) = (*value, other) { *value = Some(n+m) }
+		}
+	}
 }
 
 
 /*
 	Here we define infix addition and multiplication for dijkstra maps. We want to allow for the ability to
-	operate on dijkstra maps both destructively (by passing the map itself) or non-destructively (by passing 
+	operate on dijkstra maps both destructively (by passing the map itself) or non-destructively (by passing
 	a reference). This requires a total of 8 definitions, four for each operation.
 */
 
-///Infix multiplication definition
+//Infix multiplication definition
 
 use std::ops::Mul;
-impl <'a> Mul<&'a DijkstraMap> for f64 {
+impl Mul<&DijkstraMap> for f64 {
 	type Output = DijkstraMap;
 
-	fn mul (self, rhs: &'a DijkstraMap) -> DijkstraMap {
+	fn mul (self, rhs: &DijkstraMap) -> DijkstraMap {
 		let mut new_map = rhs.clone();
-		for j in 0..new_map.map.len() {
-			for i in 0..new_map.map[0].len() {
-				match new_map.map[j][i] {
-					DijkstraTile::Value(n) => new_map.map[j][i] = DijkstraTile::Value(n*self),
-					_ => (),
-				};
-			};
-		};
+		new_map.scale(self);
 		new_map
 	}
 }
 
-impl <'a>Mul<f64> for &'a DijkstraMap {
+impl Mul<f64> for &DijkstraMap {
 	type Output = DijkstraMap;
 
 	fn mul (self, rhs: f64) -> DijkstraMap {
 		let mut new_map = self.clone();
-		for j in 0..new_map.map.len() {
-			for i in 0..new_map.map[0].len() {
-				match new_map.map[j][i] {
-					DijkstraTile::Value(n) => new_map.map[j][i] = DijkstraTile::Value(n*rhs),
-					_ => (),
-				};
-			};
-		};
+		new_map.scale(rhs);
 		new_map
 	}
 }
@@ -187,17 +458,9 @@ impl <'a>Mul<f64> for &'a DijkstraMap {
 impl  Mul<DijkstraMap> for f64 {
 	type Output = DijkstraMap;
 
-	fn mul (self, rhs: DijkstraMap) -> DijkstraMap {
-		let mut new_map = rhs.clone();
-		for j in 0..new_map.map.len() {
-			for i in 0..new_map.map[0].len() {
-				match new_map.map[j][i] {
-					DijkstraTile::Value(n) => new_map.map[j][i] = DijkstraTile::Value(n*self),
-					_ => (),
-				};
-			};
-		};
-		new_map
+	fn mul (self, mut rhs: DijkstraMap) -> DijkstraMap {
+		rhs.scale(self);
+		rhs
 	}
 }
 
@@ -205,39 +468,20 @@ impl Mul<f64> for DijkstraMap {
 	type Output = DijkstraMap;
 
 	fn mul (mut self, rhs: f64) -> DijkstraMap {
-		for j in 0..self.map.len() {
-			for i in 0..self.map[0].len() {
-				match self.map[j][i] {
-					DijkstraTile::Value(n) => self.map[j][i] = DijkstraTile::Value(n*rhs),
-					_ => (),
-				};
-			};
-		};
+		self.scale(rhs);
 		self
 	}
 }
 
-///Infix addition definition
+//Infix addition definition
 use std::ops::Add;
 
-impl <'a,'b>Add<&'a DijkstraMap> for &'b DijkstraMap {
+impl <'a>Add<&'a DijkstraMap> for &DijkstraMap {
 	type Output = DijkstraMap;
 
 	fn add(self,rhs: &'a DijkstraMap) -> DijkstraMap {
 		let mut new_map = self.clone();
-		for j in 0..new_map.map.len() {
-			for i in 0..new_map.map[0].len() {
-				match new_map.map[j][i] {
-					DijkstraTile::Value(n) => {
-						match rhs.map[j][i] {
-							DijkstraTile::Value(m) => new_map.map[j][i] = DijkstraTile::Value(n+m),
-							_ => (),
-						}
-					}
-					_ => (),
-				};
-			};
-		};
+		new_map.add_map(rhs);
 		new_map
 	}
 }
@@ -246,41 +490,17 @@ impl <'a>Add<&'a DijkstraMap> for DijkstraMap {
 	type Output = DijkstraMap;
 
 	fn add(mut self,rhs: &'a DijkstraMap) -> DijkstraMap {
-		for j in 0..self.map.len() {
-			for i in 0..self.map[0].len() {
-				match self.map[j][i] {
-					DijkstraTile::Value(n) => {
-						match rhs.map[j][i] {
-							DijkstraTile::Value(m) => self.map[j][i] = DijkstraTile::Value(n+m),
-							_ => (),
-						}
-					}
-					_ => (),
-				};
-			};
-		};
+		self.add_map(rhs);
 		self
 	}
 }
 
-impl <'a>Add<DijkstraMap> for &'a DijkstraMap {
+impl Add<DijkstraMap> for &DijkstraMap {
 	type Output = DijkstraMap;
 
 	fn add(self,rhs: DijkstraMap) -> DijkstraMap {
 		let mut new_map = self.clone();
-		for j in 0..new_map.map.len() {
-			for i in 0..new_map.map[0].len() {
-				match new_map.map[j][i] {
-					DijkstraTile::Value(n) => {
-						match rhs.map[j][i] {
-							DijkstraTile::Value(m) => new_map.map[j][i] = DijkstraTile::Value(n+m),
-							_ => (),
-						}
-					}
-					_ => (),
-				};
-			};
-		};
+		new_map.add_map(&rhs);
 		new_map
 	}
 }
@@ -289,19 +509,200 @@ impl Add<DijkstraMap> for DijkstraMap {
 	type Output = DijkstraMap;
 
 	fn add(mut self,rhs: DijkstraMap) -> DijkstraMap {
-		for j in 0..self.map.len() {
-			for i in 0..self.map[0].len() {
-				match self.map[j][i] {
-					DijkstraTile::Value(n) => {
-						match rhs.map[j][i] {
-							DijkstraTile::Value(m) => self.map[j][i] = DijkstraTile::Value(n+m),
-							_ => (),
-						}
-					}
-					_ => (),
-				};
-			};
-		};
+		self.add_map(&rhs);
 		self
 	}
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::Rng;
+	use dungeon::Map;
+	use rng::GameRng;
+
+	/// One random change to a map
+	#[derive(Debug)]
+	enum Change {
+		AddGoal(Coord),
+		AddSeed(Coord, f64),
+		RemoveGoal(Coord),
+		SetCost(Coord, Option<f64>),
+	}
+
+	/// A random change. Goal tiles always cost 1 to step onto when a map is built from scratch, so changes
+	/// that would give a goal another cost are replaced by ones that give it a cost of 1.
+	fn random_change(rng: &mut GameRng, costs: &Grid<Option<f64>>, seeds: &Grid<Option<f64>>) -> Change {
+		let c = (rng.gen_range(0, costs.width()), rng.gen_range(0, costs.height()));
+		let costly = costs[c].is_some_and(|cost| cost != 1.0);
+		match rng.gen_range(0, 4) {
+			0 | 1 if costly => Change::SetCost(c, Some(1.0)),
+			0 => Change::AddGoal(c),
+			1 => Change::AddSeed(c, rng.gen_range(-5, 5) as f64),
+			2 => {
+				// Removing a goal that is not there does nothing, so usually pick an existing one
+				let goals: Vec<Coord> = seeds.coords().filter(|&g| seeds[g].is_some()).collect();
+				match goals.is_empty() {
+					true => Change::RemoveGoal(c),
+					false => Change::RemoveGoal(goals[rng.gen_range(0, goals.len())]),
+				}
+			}
+			_ => Change::SetCost(c, match rng.gen_range(0, 3) {
+				0 => None,
+				1 => Some(1.0),
+				_ if seeds[c].is_some() => Some(1.0),
+				_ => Some(rng.gen_range(1, 6) as f64),
+			}),
+		}
+	}
+
+	/// Apply a change to the map, and to the costs and seeds it would be rebuilt from
+	fn apply(map: &mut DijkstraMap, costs: &mut Grid<Option<f64>>, seeds: &mut Grid<Option<f64>>, change: &Change) {
+		match *change {
+			Change::AddGoal(c) => { map.add_goal(c); seeds[c] = Some(0.0) }
+			Change::AddSeed(c, s) => { map.add_seed(c, s); seeds[c] = Some(s) }
+			Change::RemoveGoal(c) => { map.remove_goal(c); seeds[c] = None }
+			Change::SetCost(c, cost) => { map.set_cost(c, cost); costs[c] = cost }
+		}
+	}
+
+	fn same(a: Option<f64>, b: Option<f64>) -> bool {
+		match (a, b) {
+			(Some(a), Some(b)) => (a - b).abs() <= 1e-9 * a.abs().max(1.0),
+			(None, None) => true,
+			_ => false,
+		}
+	}
+
+	/// The costs of a generated level, as the maps see them
+	fn level_costs(seed: u64, size: usize) -> Grid<Option<f64>> {
+		let level = Map::new(size, &mut GameRng::new(seed));
+		level.grid.map(|tile| match tile.is_passable() {
+			true => Some(tile.cost()),
+			false => None,
+		})
+	}
+
+	/// Distances from the goals by breadth first search, the way maps of unit costs were first built
+	fn breadth_first(tiles: &Grid<DijkstraTile>) -> Grid<Option<f64>> {
+		let mut values = tiles.map(|tile| match *tile {
+			DijkstraTile::Goal => Some(0.0),
+			_ => None,
+		});
+		let mut queue: VecDeque<Coord> = tiles.coords().filter(|&c| values[c].is_some()).collect();
+		while let Some(c) = queue.pop_front() {
+			for next in tiles.neighbours(c) {
+				if values[next].is_none() && tiles[next].cost().is_some() {
+					values[next] = values[c].map(|v| v + 1.0);
+					queue.push_back(next);
+				}
+			}
+		}
+		values
+	}
+
+	#[test]
+	fn unit_costs_match_breadth_first() {
+		for seed in 0..20u64 {
+			let size = [42, 100][seed as usize % 2];
+			let mut tiles = level_costs(seed, size).map(|cost| match *cost {
+				Some(_) => DijkstraTile::Passable,
+				None => DijkstraTile::Impassable,
+			});
+			tiles[(1,1)] = DijkstraTile::Goal;
+			let (map, expected) = (DijkstraMap::new(&tiles), breadth_first(&tiles));
+			for c in tiles.coords() {
+				assert!(same(map.value(c), expected[c]), "seed {}: tile {:?} is {:?} but should be {:?}", seed, c, map.value(c), expected[c]);
+			}
+		}
+	}
+
+	#[test]
+	fn updates_match_rebuilds() {
+		// Many short runs of random changes on small levels
+		for seed in 0..200u64 {
+			let mut rng = GameRng::new(seed);
+			let mut costs = level_costs(seed, 20 + (seed as usize % 3) * 10);
+			let mut seeds = Grid::new(costs.width(), costs.height(), None);
+			seeds[(1,1)] = Some(0.0);
+			let mut map = DijkstraMap::from_costs(&costs, &seeds);
+			for _ in 0..50 {
+				let change = random_change(&mut rng, &costs, &seeds);
+				apply(&mut map, &mut costs, &mut seeds, &change);
+				let fresh = DijkstraMap::from_costs(&costs, &seeds);
+				for c in costs.coords() {
+					assert!(same(map.value(c), fresh.value(c)), "seed {}: after {:?}, tile {:?} is {:?} but should be {:?}",
+						seed, change, c, map.value(c), fresh.value(c));
+				}
+			}
+		}
+	}
+
+	/// A map towards the `G` of a picture, the creature at `@` that is looking for a way there and the tiles
+	/// held by other creatures, drawn as `c`
+	fn picture(rows: &[&str]) -> (DijkstraMap, Coord, HashSet<Coord>) {
+		let mut tiles = Grid::new(rows[0].len(), rows.len(), DijkstraTile::Impassable);
+		let (mut from, mut occupied) = ((0,0), HashSet::new());
+		for (j, row) in rows.iter().enumerate() {
+			for (i, ch) in row.chars().enumerate() {
+				match ch {
+					'#' => continue,
+					'G' => tiles[(i,j)] = DijkstraTile::Goal,
+					'@' => from = (i,j),
+					'c' => { occupied.insert((i,j)); }
+					_ => (),
+				}
+				if ch != 'G' { tiles[(i,j)] = DijkstraTile::Passable }
+			}
+		}
+		(DijkstraMap::new(&tiles), from, occupied)
+	}
+
+	#[test]
+	fn steps_around_a_creature_in_a_room() {
+		let (map, from, occupied) = picture(&[
+			"#########",
+			"#.......#",
+			"#.@c..G.#",
+			"#.......#",
+			"#########",
+		]);
+		assert_eq!(map.get_next_step(from), (3,2));
+		let step = map.get_next_step_around(from, &occupied);
+		assert!(!occupied.contains(&step), "stepped onto {:?}", step);
+		assert!(map.value(step) < map.value(from), "stepped away from the goal to {:?}", step);
+	}
+
+	#[test]
+	fn waits_rather_than_take_a_long_way_around() {
+		let (map, from, occupied) = picture(&[
+			"#################",
+			"#...............#",
+			"#.#############.#",
+			"#.#############.#",
+			"#.#############.#",
+			"#.#############.#",
+			"#.#############.#",
+			"#.#############.#",
+			"#.#############.#",
+			"#@c...........G.#",
+			"#################",
+		]);
+		assert_eq!(map.get_next_step_around(from, &occupied), from);
+	}
+
+	#[test]
+	fn never_steps_onto_a_creature_in_a_corridor() {
+		let corridor = "#@.....G#";
+		for blocker in 2..7 {
+			let mut row: Vec<char> = corridor.chars().collect();
+			row[blocker] = 'c';
+			let row: String = row.into_iter().collect();
+			let (map, from, occupied) = picture(&["#########", &row, "#########"]);
+			let step = map.get_next_step_around(from, &occupied);
+			assert!(!occupied.contains(&step), "{}: stepped onto the creature", row);
+			let expected = if blocker == 2 { from } else { (2,1) };
+			assert_eq!(step, expected, "{}", row);
+		}
+	}
+}
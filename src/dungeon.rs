@@ -1,127 +1,172 @@
 //!Definitions for the entities making up the dungeon as a whole and its tiles
 
-use piston_window::*;
-use gfx_device_gl::{Resources, Output, CommandBuffer};
-use gfx_graphics::GfxGraphics;
-use sprite::Sprite;
-
-///A quick and dirty LOS computation algorithm. Draws a line to each cell being checked separately.
-
-pub fn fov(map: &Map, x:isize, y:isize, radius: isize) -> Vec<(usize,usize)> {
-	let mut output = vec![];
-	for i in -radius..radius+1 {
-		for j in -radius..radius+1 {
-			if i*i+j*j<radius*radius { 
-				match los(map,x,y,x+i,y+j) {
-					Some((a,b)) => output.push((a,b)),
-					_ => (),
-				}
-				
-			}
-		}
-	};
-	output
+///How far the player can see
+pub const VISION_RADIUS: usize = 14;
+
+///Light levels run from 0, too dark to see anything, to LIGHT_FULL
+pub const LIGHT_FULL: u8 = 9;
+///How far a torch lights
+pub const TORCH_RADIUS: usize = 7;
+
+///The light the first level of the dungeon is bathed in. Every level further down is a step darker.
+pub fn ambient_light(depth: usize) -> u8 {
+	3usize.saturating_sub(depth.saturating_sub(1)) as u8
 }
 
-pub fn los(map: &Map, x0:isize, y0:isize, x1:isize, y1:isize) -> Option<(usize,usize)> {
-	if x1 < 0 || y1 < 0 || x1 >= map.width() as isize || y1 >= map.height() as isize { return None };
-	let dx = x1 - x0;
-	let dy = y1 - y0;
-	if dx == 0 && dy == 0 { return Some((x0 as usize,y0 as usize)) };
-	let sx = match x0 < x1 {
-		true => 1,
-		false => -1,
-	};
-	let sy = match y0 < y1 {
-		true => 1,
-		false => -1,
-	};
-    // sx and sy are switches that enable us to compute the LOS in a single quarter of x/y plan
-    let mut xnext = x0;
-    let mut ynext = y0;
-    let denom = ((dx * dx + dy * dy) as f64).sqrt();
-    while xnext != x1 || ynext != y1 {
-        // check map bounds here if needed
-        if !map.grid[ynext as usize][xnext as usize].is_transvisible() && !(xnext==x0 && ynext==y0) // or any equivalent
-        {
-            return Some((xnext as usize, ynext as usize))
-        }
-        // Line-to-point distance formula < 0.5
-        if ((dy * (xnext - x0 + sx) - dx * (ynext - y0)) as f64).abs() / denom < 0.5 { xnext += sx; }
-            
-        else if ((dy * (xnext - x0) - dx * (ynext - y0 + sy)) as f64).abs() / denom < 0.5 { ynext += sy; }
-        else
-        {
-            xnext += sx;
-            ynext += sy;
-        }
-    }
-    Some((x1 as usize, y1 as usize))
+///The light a source of the given radius sheds on a tile at the given offset from it. It is full at the
+///source and fades to 1 at the edge of the radius.
+pub fn light_at(radius: usize, di: isize, dj: isize) -> u8 {
+	let (d, r) = (((di*di + dj*dj) as f64).sqrt(), radius as f64);
+	match d < r {
+		true => (LIGHT_FULL as f64 * (r - d) / r).ceil() as u8,
+		false => 0,
+	}
 }
 
-use dijkstra_map::DijkstraMap;
+///The tiles visible from (x,y) within the radius, by symmetric shadowcasting: one tile sees another exactly
+///when the other sees it back. Walls and closed doors bounding the view are included.
+pub fn fov(map: &Map, x:isize, y:isize, radius: isize) -> Vec<Coord> {
+	if radius <= 0 || x < 0 || y < 0 || !map.grid.contains((x as usize, y as usize)) { return vec![] }
+	field_of_view((x as usize, y as usize), radius as usize, map.width(), map.height(), |c| !map.tile(c).is_transvisible())
+}
 
-pub struct Map{ pub grid : Vec< Vec< Tile > > } /// A vector of rows
+use dijkstra_map::{DijkstraMap,DijkstraTile};
+use fov::{Visibility,field_of_view};
+use generator::{GeneratorKind,START};
+use grid::{Coord,Grid};
+use rand::Rng;
+use std::mem;
+
+pub struct Map{
+	pub grid : Grid<Tile>,
+	ambient: u8,	//light reaching every tile, whatever else lights it
+}
 
 impl Map {
 
-	pub fn new(w: &PistonWindow, size: usize) -> Map { //size indicates the width and height of the map.
-		let terrain = generate_fractal_dungeon(size,size);
-		let mut grid = vec![];
-		for j in 0..terrain.len() {
-			let mut row = vec![];
-			for i in 0..terrain[j].len() {
-				row.push( Tile::new(w,terrain[j][i].clone(),i,j) );
-			}
-			grid.push(row);
-		}
-		Map{
-			grid: grid,
+	pub fn new<R: Rng>(size: usize, rng: &mut R) -> Map { //size indicates the width and height of the map.
+		Map::from_terrain(GeneratorKind::RecursiveSplit.generate(size,size,rng))
+	}
+
+	/// Generate the level at the given depth, counting from 1, with the given generator. Every level has
+	/// stairs down, and all but the first have stairs up.
+	pub fn level<R: Rng>(size: usize, depth: usize, generator: GeneratorKind, rng: &mut R) -> Map {
+		let mut terrain = generator.generate(size,size,rng);
+		// The player starts the game at START, so keep stairs away from there
+		let mut spots: Vec<Coord> = terrain.coords().filter(|&c| terrain[c] == TerrainType::Floor && c != START).collect();
+		rng.shuffle(&mut spots);
+		if let Some(c) = spots.pop() { terrain[c] = TerrainType::StairsDown }
+		if depth > 1 {
+			if let Some(c) = spots.pop() { terrain[c] = TerrainType::StairsUp }
 		}
+		let mut map = Map::from_terrain(terrain);
+		map.set_ambient(ambient_light(depth));
+		map
+	}
+
+	/// Build an unexplored map from a grid of terrain. It is in full light until told otherwise.
+	pub fn from_terrain(terrain: Grid<TerrainType>) -> Map {
+		let mut map = Map{
+			grid: Grid::from_fn(terrain.width(), terrain.height(), |(i,j)| Tile::new(terrain[(i,j)].clone(),i,j)),
+			ambient: LIGHT_FULL,
+		};
+		map.update_glow();
+		map
 	}
 
 	///Functions to retrive information
-	pub fn tile(&self, i: usize, j: usize) -> &Tile { &self.grid[j as usize][i as usize] }
-	pub fn width(&self) -> usize { self.grid[0].len() }
-	pub fn height(&self) -> usize { self.grid.len() }
+	pub fn tile(&self, c: Coord) -> &Tile { &self.grid[c] }
+	pub fn tile_mut(&mut self, c: Coord) -> &mut Tile { &mut self.grid[c] }
+	pub fn width(&self) -> usize { self.grid.width() }
+	pub fn height(&self) -> usize { self.grid.height() }
 
-	pub fn update_vision(&mut self, origin: (usize,usize)) {
-		let (i,j) = origin;
-		//first, wipe all tiles from vision
-		for row in self.grid.iter_mut() {
-			for tile in row.iter_mut() {
-				tile.unsee();
-			}
+	/// The first tile with the given terrain, such as a level's stairs
+	pub fn find(&self, terrain: TerrainType) -> Option<Coord> {
+		self.grid.coords().find(|&c| *self.grid[c].terrain() == terrain)
+	}
+
+	pub fn ambient(&self) -> u8 { self.ambient }
+	pub fn set_ambient(&mut self, light: u8) {
+		self.ambient = light;
+		self.update_glow();
+	}
+
+	/// The light a source of the given radius at `origin` sheds on each tile it can see
+	fn shine(&self, origin: Coord, radius: usize) -> Vec<(Coord,u8)> {
+		let (x, y) = (origin.0 as isize, origin.1 as isize);
+		field_of_view(origin, radius, self.width(), self.height(), |c| !self.tile(c).is_transvisible()).into_iter()
+			.map(|(i,j)| ((i,j), light_at(radius, i as isize - x, j as isize - y)))
+			.collect()
+	}
+
+	/// Work out the light that stays put: the ambient light, torches and light left by spells. This needs
+	/// doing again whenever any of those change.
+	pub fn update_glow(&mut self) {
+		for tile in self.grid.iter_mut() { tile.glow = self.ambient.max(tile.magic) }
+		let torches: Vec<Coord> = self.grid.coords().filter(|&c| *self.tile(c).terrain() == TerrainType::Torch).collect();
+		for torch in torches {
+			for (c, light) in self.shine(torch, TORCH_RADIUS) { self.grid[c].glow = self.grid[c].glow.max(light) }
 		}
-		//Then, mark all tiles in fov as visible and explored
-		for coordinate in fov(self,i as isize,j as isize,14) { self.grid[coordinate.1][coordinate.0].see(); }
+		// The lights creatures carry are left out until the next `update_light`
+		for tile in self.grid.iter_mut() { tile.light = tile.glow }
 	}
 
-	pub fn render(&self, g: &mut GfxGraphics<Resources, CommandBuffer<Resources>, Output>, view: math::Matrix2d) {
-		for row in self.grid.iter() {
-			for tile in row.iter() {
-				tile.render(g,view);
-			}
+	/// Light the map for the current turn: the light that stays put, and the lights creatures carry, given by
+	/// where they are and how far they shine
+	pub fn update_light(&mut self, lamps: &[(Coord,usize)]) {
+		for tile in self.grid.iter_mut() { tile.light = tile.glow }
+		for &(origin, radius) in lamps.iter() {
+			for (c, light) in self.shine(origin, radius) { self.grid[c].light = self.grid[c].light.max(light) }
 		}
 	}
+
+	/// Leave light behind on the tiles a source of the given radius at `origin` would light, as the light
+	/// spell does. Returns the tiles lit.
+	pub fn light_area(&mut self, origin: Coord, radius: usize) -> Vec<Coord> {
+		let lit = self.shine(origin, radius);
+		for &(c, light) in lit.iter() { self.grid[c].magic = self.grid[c].magic.max(light) }
+		self.update_glow();
+		lit.into_iter().map(|(c,_)| c).collect()
+	}
+
+	/// Change the terrain of the tile at c, as when a door opens or a wall is dug out. The light that stays
+	/// put is worked out again if the change lets light through, blocks it, or adds or removes a torch.
+	/// Returns the terrain replaced, or None if c is outside the map.
+	pub fn set_terrain(&mut self, c: Coord, terrain: TerrainType) -> Option<TerrainType> {
+		let tile = self.grid.get_mut(c)?;
+		let relight = tile.transvisible != terrain.is_transvisible() || tile.terrain == TerrainType::Torch || terrain == TerrainType::Torch;
+		let old = tile.set_terrain(terrain);
+		if relight { self.update_glow() }
+		Some(old)
+	}
+
+	/// The field of view from the given tile, kept as a set
+	pub fn visibility(&self, origin: Coord, radius: usize) -> Visibility {
+		Visibility::new(origin, radius, self.width(), self.height(), |c| !self.tile(c).is_transvisible())
+	}
+
+	/// Mark what the player sees as visible and explored. The view may reach further than the player's own
+	/// sight, which is given separately. Tiles in darkness cannot be seen however near they are, so the light
+	/// must be up to date.
+	pub fn update_vision(&mut self, view: &Visibility, sight: usize) {
+		//first, wipe all tiles from vision
+		for tile in self.grid.iter_mut() { tile.unsee(); }
+		//Then, mark all lit tiles in sight as visible and explored
+		for coordinate in view.tiles().filter(|&c| view.within(c, sight)) {
+			if self.grid[coordinate].light > 0 { self.grid[coordinate].see(); }
+		}
+	}
+
 	//Function that returns a dijkstra map given the input goal cells
-	pub fn get_dijkstra_map(&self, goals: Vec<(usize,usize)>) -> DijkstraMap {
-		use dijkstra_map::DijkstraTile;
+	pub fn get_dijkstra_map(&self, goals: Vec<Coord>) -> DijkstraMap {
 		//Create a map of dijkstra tiles
-		let mut map: Vec<Vec<DijkstraTile>> = self.grid.iter().map(
-			|row| row.iter().map(
-				|tile| match tile.is_passable() {
-					true => DijkstraTile::Passable,
-					false => DijkstraTile::Impassable,
-				}
-			).collect()
-		).collect();
+		let mut map = self.grid.map(|tile| tile.dijkstra_tile());
 
 		//Add the goals. Only goals on passable ground are added.
-		for (i,j) in goals {
-			match map[j][i] {
-				DijkstraTile::Passable => map[j][i] = DijkstraTile::Goal,
-				_ => (),
+		for c in goals {
+			match map.get(c) {
+				Some(&DijkstraTile::Impassable) | None => (),
+				_ => map[c] = DijkstraTile::Goal,
 			}
 		};
 
@@ -130,131 +175,17 @@ impl Map {
 	}
 }
 
-fn generate_fractal_dungeon(width:usize,height:usize) -> Vec<Vec<TerrainType>> {
-	//Initialize dungeon to walls with floor in middle
-	let mut dungeon = vec![];
-		for n in 0..height { // iterate over the rows
-			let mut row = vec![];
-			// iterate over the tiles
-			for m in 0..width { 
-				match (m,n) {
-					(m,n) if m==0 || n==0 || m==width-1 || n==height-1 => row.push( TerrainType::Wall ),
-					_ => row.push( TerrainType::Floor ),
-				}
-			};
-			dungeon.push(row);
-		};
-	/// Define recursive splitting function
-	/// This function takes a rectangle and splits it into two smaller rectangles
-	/// by drawing a line through it. It places up to 2 doors and 1 window on that line.
-	/// The recursion terminates once the rectangles reach size 3.
-	/// Lines are only drawn on even numbered rows/columns
-	fn rec_split(d: &mut Vec<Vec<TerrainType>>, x0:usize, x1:usize, y0:usize, y1:usize) {
-		use rand;
-		use rand::*;
-		let (width,height) = (x1-x0+1,y1-y0+1);
-		let mut terrains = vec![TerrainType::Window,TerrainType::Door,TerrainType::Door];
-		let mut locations = vec![];
-		let mut rng = rand::thread_rng();
-		let mut splitpoints = vec![];
-		// First, exit if the rectangle is too small
-		if width <= 5 || height <= 5 { return; }
-
-		// Split rectangle across longer axis
-		match width < height {
-			true => { // draw horizontal line. First, select a random row
-				let mut row = y0+4;
-				//Select a point.
-				for y in y0+2..y1-2 { if y%2==0 { splitpoints.push(y) } };
-				rng.shuffle(&mut splitpoints);
-				loop {
-					match splitpoints.pop() {
-						Some(y) => match (d[y][x0].clone(),d[y][x1].clone()) {
-							(TerrainType::Wall,TerrainType::Wall) => {
-								for x in x0..x1 { 
-									row=y; 
-									d[y][x] = TerrainType::Wall;
-								};
-								break; 
-							},
-							_ => (),
-						},
-						_ => return,
-					}
-				}
-				//Then we randomly place up to 2 doors and 1 window on the new wall
-				//First, get a vector of even locations that are free
-				
-				
-				for x in x0+2..x1-2 { if x%2==0 { locations.push(x) } };
-				//Then shuffle it. Pop the first three elements (up to) and turn them into doors and a window
-				rng.shuffle(&mut locations);
-				for _ in 0..3 {
-					let terrain_type = match terrains.pop() {
-						Some(t) => t,
-						None => unreachable!(),
-					};
-					match locations.pop() {
-						Some(x) => d[row][x] = terrain_type,
-						None => break,
-					};
-				}
-				// Now call this function on each of the two smaller rooms
-				
-				rec_split(d, x0, x1, y0, row);
-				rec_split(d, x0, x1, row, y1);
-			}
-			false => { // draw vertical line. First, select a random column
-				let mut column = x0 + 4;
-				//Select a point.
-				for x in x0+2..x1-2 { if x%2==0 { splitpoints.push(x) } };
-				rand::thread_rng().shuffle(&mut splitpoints);
-				loop {
-					match splitpoints.pop() {
-						Some(x) => match (d[y0][x].clone(),d[y1][x].clone()) {
-							(TerrainType::Wall,TerrainType::Wall) => {
-								for y in y0..y1 { 
-									column = x;  
-									d[y][x] = TerrainType::Wall;
-								};
-								break;
-							},
-							_ => (),
-						},
-						_ => return,
-					}
-				}
-				//Then we randomly place up to 2 doors and 1 window on the new wall
-				//First, get a vector of even locations that are free
-				for y in y0+2..y1-2 { if y%2==0 { locations.push(y) } };
-				//Then shuffle it. Pop the first three elements (up to) and turn them into doors and a window
-				rand::thread_rng().shuffle(&mut locations);
-				for _ in 0..3 {
-					let terrain_type = match terrains.pop() {
-						Some(t) => t,
-						None => unreachable!(),
-					};
-					match locations.pop() {
-						Some(y) => d[y][column] = terrain_type,
-						None => break,
-					};
-				}
-				// Now call this function on each of the two smaller rooms
-				rec_split(d, x0, column, y0, y1);
-				rec_split(d, column, x1, y0, y1);
-			}
-		}
-	}
-	rec_split(&mut dungeon,0,width-1,0,height-1);
-	dungeon
-}
-
-
+#[derive(Debug,PartialEq)]
 pub enum TerrainType {
 	Wall,
 	Floor,
 	Window,
-	Door,
+	Door,		//a closed door
+	OpenDoor,
+	LockedDoor,
+	StairsUp,
+	StairsDown,
+	Torch,	//a wall with a torch on it
 }
 
 impl Clone for TerrainType {
@@ -264,57 +195,147 @@ impl Clone for TerrainType {
 			TerrainType::Floor => TerrainType::Floor,
 			TerrainType::Window => TerrainType::Window,
 			TerrainType::Door => TerrainType::Door,
+			TerrainType::OpenDoor => TerrainType::OpenDoor,
+			TerrainType::LockedDoor => TerrainType::LockedDoor,
+			TerrainType::StairsUp => TerrainType::StairsUp,
+			TerrainType::StairsDown => TerrainType::StairsDown,
+			TerrainType::Torch => TerrainType::Torch,
 		}
 	}
 }
 
-/// A tile of a map
+impl TerrainType {
+	/// Whether creatures can get onto the terrain, if need be by opening the door it is
+	pub fn is_passable(&self) -> bool {
+		!matches!(*self, TerrainType::Wall | TerrainType::Window | TerrainType::Torch)
+	}
+
+	/// Whether the terrain can be seen through
+	pub fn is_transvisible(&self) -> bool {
+		!matches!(*self, TerrainType::Wall | TerrainType::Door | TerrainType::LockedDoor | TerrainType::Torch)
+	}
+
+	/// Whether the terrain is a door, whatever state it is in
+	pub fn is_door(&self) -> bool {
+		matches!(*self, TerrainType::Door | TerrainType::OpenDoor | TerrainType::LockedDoor)
+	}
+
+	/// Whether the terrain is a door that has to be opened before anyone can step onto it
+	pub fn is_closed(&self) -> bool {
+		matches!(*self, TerrainType::Door | TerrainType::LockedDoor)
+	}
+
+	/// The character used for the terrain in the terminal and in save files
+	pub fn glyph(&self) -> char {
+		match *self {
+			TerrainType::Wall => '#',
+			TerrainType::Floor => '.',
+			TerrainType::Door => '+',
+			TerrainType::OpenDoor => '\'',
+			TerrainType::LockedDoor => '%',
+			TerrainType::Window => '=',
+			TerrainType::StairsUp => '<',
+			TerrainType::StairsDown => '>',
+			TerrainType::Torch => '*',
+		}
+	}
+
+	pub fn from_glyph(glyph: char) -> Option<TerrainType> {
+		match glyph {
+			'#' => Some(TerrainType::Wall),
+			'.' => Some(TerrainType::Floor),
+			'+' => Some(TerrainType::Door),
+			'\'' => Some(TerrainType::OpenDoor),
+			'%' => Some(TerrainType::LockedDoor),
+			'=' => Some(TerrainType::Window),
+			'<' => Some(TerrainType::StairsUp),
+			'>' => Some(TerrainType::StairsDown),
+			'*' => Some(TerrainType::Torch),
+			_ => None,
+		}
+	}
+}
+
+/// A tile of a map. Front-ends decide how to draw it from its terrain type.
 pub struct Tile {
-	sprite: Sprite,
+	terrain: TerrainType,
 	passable : bool,	//can you move onto it?
 	transvisible: bool,	//can you see through it?
 	visible: bool,	// can it be seen RIGHT NOW?
 	explored: bool, //has it been seen before?
+	magic: u8,	//light left on it by spells
+	glow: u8,	//light that stays put: ambient light, torches and magic
+	light: u8,	//light this turn, adding what creatures carry
     pub i: usize,
     pub j: usize,
 }
 
 impl Tile {
-	pub fn new(w: &PistonWindow, terrain: TerrainType, i:usize, j: usize) -> Tile {
-		let (sprite,passable,transvisible) = match terrain {
-			TerrainType::Wall => (Sprite::new(w,"wall.png"),false,false),
-			TerrainType::Floor => (Sprite::new(w,"floor.png"),true,true),
-			TerrainType::Door => (Sprite::new(w,"door.png"), true, false),
-			TerrainType::Window => (Sprite::new(w,"window.png"), false, true),
-			};
+	pub fn new(terrain: TerrainType, i:usize, j: usize) -> Tile {
+		let (passable,transvisible) = (terrain.is_passable(), terrain.is_transvisible());
 		Tile {
-				sprite: sprite,
+				terrain: terrain,
 				passable : passable,
 				transvisible: transvisible,
 				visible: false,
 				explored: false,
+				magic: 0,
+				glow: 0,
+				light: 0,
 			    i: i,
 			    j: j,
 		}
 	}
-	pub fn render(&self, g: &mut GfxGraphics<Resources, CommandBuffer<Resources>, Output>, view: math::Matrix2d) {
-		//if is inlineofsight
-		if self.visible { self.sprite.render(self.x(),self.y(),g,view) }
-		else if self.explored {
-			self.sprite.render(self.x(),self.y(),g,view);
-			rectangle([0.0, 0.0, 0.0, 0.5], rectangle::square(0.0, 0.0, 32.0), view.trans(self.x(),self.y()), g); 
-		}
-		//else if self.explored render with rectangle over it
+
+    /// Give the tile new terrain, which it can be walked on and seen through as. The map owns the light, so
+    /// changes go through `Map::set_terrain`.
+    fn set_terrain(&mut self, terrain: TerrainType) -> TerrainType {
+        self.passable = terrain.is_passable();
+        self.transvisible = terrain.is_transvisible();
+        mem::replace(&mut self.terrain, terrain)
     }
 
     pub fn see(&mut self) { self.visible = true; self.explored = true }
     pub fn unsee(&mut self) { self.visible = false; }
+    pub fn explore(&mut self) { self.explored = true; }	//mark as remembered without seeing it (e.g. when loading a save)
     pub fn visible(&self) -> bool { self.visible }
-
-    fn x(&self) -> f64 { (self.i as f64)*32.0 }
-    fn y(&self) -> f64 { (self.j as f64)*32.0 }
+    pub fn terrain(&self) -> &TerrainType { &self.terrain }
 
     pub fn is_passable(&self) -> bool {self.passable}
+
+    /// How much it costs to step onto the tile. A closed door has to be opened first, which takes as long
+    /// as a step.
+    pub fn cost(&self) -> f64 {
+        match self.terrain {
+            TerrainType::Door => 2.0,
+            _ => 1.0,
+        }
+    }
+
+    /// The cost of stepping onto the tile as the dijkstra maps see it, or None where they do not go. Locked
+    /// doors are left out: only the player can get through them, by picking the lock first.
+    pub fn map_cost(&self) -> Option<f64> {
+        match self.passable && self.terrain != TerrainType::LockedDoor {
+            true => Some(self.cost()),
+            false => None,
+        }
+    }
+
+    /// The tile as seen by the dijkstra map algorithm, ignoring creatures
+    pub fn dijkstra_tile(&self) -> DijkstraTile {
+        match self.map_cost() {
+            None => DijkstraTile::Impassable,
+            Some(1.0) => DijkstraTile::Passable,
+            Some(c) => DijkstraTile::Cost(c),
+        }
+    }
     pub fn is_transvisible(&self) -> bool {self.transvisible}
     pub fn is_explored(&self) -> bool {self.explored}
+
+    /// How brightly the tile is lit this turn, from 0 to LIGHT_FULL
+    pub fn light(&self) -> u8 {self.light}
+    /// The light spells have left on the tile
+    pub fn magic(&self) -> u8 {self.magic}
+    /// Set the light left by spells, for loading a save. The map's glow must be updated afterwards.
+    pub fn set_magic(&mut self, light: u8) { self.magic = light.min(LIGHT_FULL) }
 }
\ No newline at end of file
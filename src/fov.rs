@@ -0,0 +1,260 @@
+/*! Field of view by symmetric shadowcasting
+
+Scans the four quarters around the viewer row by row, moving away from it. Each row is narrowed by the
+walls found in the rows before, and a wall in the middle of a row splits the rest of the scan in two.
+
+A tile that does not block sight is only seen if the viewer's centre is visible from its centre, which
+makes vision symmetric between such tiles: if A sees B then B sees A. Walls are seen whenever any part of
+them is lit, so rooms show their outline. Slopes are kept as exact fractions, so results do not depend on rounding.
+
+See https://www.albertford.com/shadowcasting/ for a longer description of the algorithm.
+*/
+
+use grid::{Coord,Grid};
+
+/// A slope from the viewer, as a fraction with a positive denominator
+#[derive(Clone,Copy)]
+struct Slope { num: isize, den: isize }
+
+/// A function from (depth, column) in a quarter to an offset from the viewer
+type Transform = fn(isize, isize) -> (isize, isize);
+
+/// A row of tiles at the given distance from the viewer, between two slopes
+struct Row { depth: isize, start: Slope, end: Slope }
+
+/// Division rounding towards negative infinity, for a positive divisor
+fn floor_div(a: isize, b: isize) -> isize { a.div_euclid(b) }
+
+impl Row {
+	/// The first and last column of the row. Tiles whose centre is exactly on an edge are included.
+	fn columns(&self) -> (isize, isize) {
+		// depth*start rounded with ties going up, and depth*end rounded with ties going down
+		let first = floor_div(2*self.depth*self.start.num + self.start.den, 2*self.start.den);
+		let last = -floor_div(-(2*self.depth*self.end.num - self.end.den), 2*self.end.den);
+		(first, last)
+	}
+
+	/// Whether the centre of the tile in the given column lies between the row's slopes
+	fn is_symmetric(&self, col: isize) -> bool {
+		col*self.start.den >= self.depth*self.start.num && col*self.end.den <= self.depth*self.end.num
+	}
+
+	fn next(&self) -> Row { Row { depth: self.depth + 1, start: self.start, end: self.end } }
+}
+
+/// The slope to the near edge of the tile in the given column
+fn slope(depth: isize, col: isize) -> Slope { Slope { num: 2*col - 1, den: 2*depth } }
+
+/// The tiles visible from `origin` within `radius`, on a grid of the given size. `is_opaque` tells which
+/// tiles block sight. The origin is always included, and so are the opaque tiles that bound the view.
+pub fn field_of_view<F: Fn(Coord) -> bool>(origin: Coord, radius: usize, width: usize, height: usize, is_opaque: F) -> Vec<Coord> {
+	let mut output = vec![origin];
+	let radius = radius as isize;
+	let (x, y) = (origin.0 as isize, origin.1 as isize);
+	// The four quarters: above, right of, below and left of the viewer
+	let quarters: [Transform; 4] = [
+		|depth, col| (col, -depth),
+		|depth, col| (depth, col),
+		|depth, col| (col, depth),
+		|depth, col| (-depth, col),
+	];
+	for transform in quarters.iter() {
+		// Tiles outside the grid block sight and are never seen
+		let tile = |depth: isize, col: isize| -> Option<Coord> {
+			let (di, dj) = transform(depth, col);
+			let (i, j) = (x + di, y + dj);
+			match i >= 0 && j >= 0 && i < width as isize && j < height as isize {
+				true => Some((i as usize, j as usize)),
+				false => None,
+			}
+		};
+		let opaque = |depth: isize, col: isize| tile(depth, col).is_none_or(&is_opaque);
+		let in_range = |depth: isize, col: isize| depth*depth + col*col < radius*radius;
+
+		let mut rows = vec![Row { depth: 1, start: Slope { num: -1, den: 1 }, end: Slope { num: 1, den: 1 } }];
+		while let Some(mut row) = rows.pop() {
+			if row.depth >= radius { continue }
+			let (first, last) = row.columns();
+			// Whether the previous tile in the row blocked sight, if there was one
+			let mut previous: Option<bool> = None;
+			for col in first..last+1 {
+				let wall = opaque(row.depth, col);
+				if (wall || row.is_symmetric(col)) && in_range(row.depth, col) {
+					if let Some(c) = tile(row.depth, col) { output.push(c) }
+				}
+				match (previous, wall) {
+					// Leaving a wall: the rest of the row is only seen past its edge
+					(Some(true), false) => row.start = slope(row.depth, col),
+					// Reaching a wall: scan what lies beyond the open stretch before it
+					(Some(false), true) => {
+						let mut next = row.next();
+						next.end = slope(row.depth, col);
+						rows.push(next);
+					}
+					_ => (),
+				}
+				previous = Some(wall);
+			}
+			if previous == Some(false) { rows.push(row.next()) }
+		}
+	}
+	// Tiles on the diagonals are reached from two quarters
+	output.sort_by_key(|&(i,j)| (j,i));
+	output.dedup();
+	output
+}
+
+/// A field of view kept as a set of tiles, so it can be asked about many tiles without being computed again.
+/// Since vision is symmetric, it also tells which tiles can see its origin.
+pub struct Visibility {
+	origin: Coord,
+	radius: usize,
+	seen: Grid<bool>,
+}
+
+impl Default for Visibility {
+	/// A view of nothing at all
+	fn default() -> Visibility { Visibility { origin: (0,0), radius: 0, seen: Grid::new(0, 0, false) } }
+}
+
+impl Visibility {
+	/// The tiles visible from `origin` within `radius`, as found by `field_of_view`
+	pub fn new<F: Fn(Coord) -> bool>(origin: Coord, radius: usize, width: usize, height: usize, is_opaque: F) -> Visibility {
+		let mut seen = Grid::new(width, height, false);
+		for c in field_of_view(origin, radius, width, height, is_opaque) { seen[c] = true }
+		Visibility { origin: origin, radius: radius, seen: seen }
+	}
+
+	pub fn origin(&self) -> Coord { self.origin }
+	pub fn radius(&self) -> usize { self.radius }
+
+	/// Whether the tile is in view
+	pub fn contains(&self, c: Coord) -> bool { self.seen.get(c) == Some(&true) }
+
+	/// Whether the tile would be in view with the given radius. Nothing beyond a radius changes what is seen
+	/// within it, so this holds for any radius up to the one the view was computed with.
+	pub fn within(&self, c: Coord, radius: usize) -> bool {
+		let (di, dj) = (c.0 as isize - self.origin.0 as isize, c.1 as isize - self.origin.1 as isize);
+		self.contains(c) && di*di + dj*dj < (radius*radius) as isize
+	}
+
+	/// Every tile in view
+	pub fn tiles(&self) -> impl Iterator<Item=Coord> + '_ { self.seen.coords().filter(move |&c| self.seen[c]) }
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashSet;
+	use dungeon::{Map,TerrainType,VISION_RADIUS,fov};
+	use generator::GeneratorKind;
+	use grid::{Coord,Grid};
+	use rng::GameRng;
+
+	// Hand-made maps. `@` is the viewer, `v` a floor tile it must see and `x` a floor tile it must not see.
+	// Every other tile is drawn as it is in save files and not checked.
+	const CASES: [(&str, &[&str]); 7] = [
+		("open room", &[
+			"#######",
+			"#vvvvv#",
+			"#vv@vv#",
+			"#vvvvv#",
+			"#######",
+		]),
+		("pillar", &[
+			"###########",
+			"#@vvvvvvvv#",
+			"#vv#xvvvvv#",
+			"#vvvxxxxx.#",
+			"#vvvvvxxxx#",
+			"###########",
+		]),
+		("closed door", &[
+			"#########",
+			"#vvv+xxx#",
+			"#v@v#xxx#",
+			"#########",
+		]),
+		("window", &[
+			"#########",
+			"#v@v=vvv#",
+			"#vvv#x..#",
+			"#########",
+		]),
+		("corridor", &[
+			"###########",
+			"#@vvvvvvvv#",
+			"####.######",
+			"####x######",
+			"###########",
+		]),
+		("doorway", &[
+			"###########",
+			"#xxxvvvxxx#",
+			"#xxxvvvxxx#",
+			"#####.#####",
+			"#####@#####",
+			"###########",
+		]),
+		("out of range", &[
+			"##################",
+			"#@vvvvvvvvvvvvvxx#",
+			"##################",
+		]),
+	];
+
+	/// Read a hand-made map, returning it with the viewer and the tiles that must and must not be seen
+	fn parse(rows: &[&str]) -> (Map, Coord, Vec<Coord>, Vec<Coord>) {
+		let (mut viewer, mut seen, mut hidden) = ((0,0), vec![], vec![]);
+		let terrain = rows.iter().enumerate().map(|(j, row)| row.chars().enumerate().map(|(i, glyph)| {
+			match glyph {
+				'@' => viewer = (i,j),
+				'v' => seen.push((i,j)),
+				'x' => hidden.push((i,j)),
+				_ => return TerrainType::from_glyph(glyph).expect("unknown glyph"),
+			}
+			TerrainType::Floor
+		}).collect()).collect();
+		(Map::from_terrain(Grid::from_rows(terrain)), viewer, seen, hidden)
+	}
+
+	/// The tiles of the map, with only those in `visible` shown
+	fn show(map: &Map, viewer: Coord, visible: &HashSet<Coord>) -> String {
+		(0..map.height()).map(|j| {
+			let line: String = (0..map.width()).map(|i| match (i,j) {
+				c if c == viewer => '@',
+				c if visible.contains(&c) => map.tile(c).terrain().glyph(),
+				_ => ' ',
+			}).collect();
+			line + "\n"
+		}).collect()
+	}
+
+	#[test]
+	fn hand_made_maps() {
+		for &(name, rows) in CASES.iter() {
+			let (map, viewer, seen, hidden) = parse(rows);
+			let visible: HashSet<Coord> = fov(&map, viewer.0 as isize, viewer.1 as isize, VISION_RADIUS as isize).into_iter().collect();
+			for c in seen.iter() { assert!(visible.contains(c), "{}: tile {:?} is unseen\n{}", name, c, show(&map, viewer, &visible)) }
+			for c in hidden.iter() { assert!(!visible.contains(c), "{}: tile {:?} is seen\n{}", name, c, show(&map, viewer, &visible)) }
+		}
+	}
+
+	#[test]
+	fn symmetric() {
+		// From every tile that does not block sight, every such tile in view must see it back. Walls and doors
+		// are seen as the outline of what lies in view, so they are left out.
+		for seed in 0..40u64 {
+			let kind = GeneratorKind::all()[seed as usize % GeneratorKind::all().len()];
+			let map = Map::level(42, 1, kind, &mut GameRng::new(seed));
+			let views: Grid<HashSet<Coord>> = Grid::from_fn(map.width(), map.height(), |(i,j)| {
+				fov(&map, i as isize, j as isize, VISION_RADIUS as isize).into_iter().collect()
+			});
+			for a in map.grid.coords().filter(|&c| map.tile(c).is_transvisible()) {
+				for &b in views[a].iter().filter(|&&b| map.tile(b).is_transvisible()) {
+					assert!(views[b].contains(&a), "{} seed {}: {:?} sees {:?} but not the other way round\n{}",
+						kind.name(), seed, a, b, show(&map, a, &views[a]));
+				}
+			}
+		}
+	}
+}
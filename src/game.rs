@@ -1,193 +1,950 @@
-//!This file stores the code for the game object and player controls
+//!This file stores the code for the game object. The game knows nothing about windows or input devices:
+//!front-ends translate their input into commands and pass them to `Game::step`.
 
-use piston_window::*;
 use dungeon::*;
 use object::*;
+use rng::GameRng;
+use grid::Coord;
+use generator::GeneratorKind;
+use spell::{SpellDef,Targeting,Effect,builtin_spells};
+use monster::{MonsterDef,builtin_monsters};
+use ai;
+use ai::{DesireMaps,Mood};
+use map_cache::MapCache;
+use fov::Visibility;
+use std::collections::{HashMap,HashSet,VecDeque};
+use std::convert::TryFrom;
+use std::mem;
 
-enum Command {
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Command {
     None,               //For when game is waiting for player to issue instruction
     Move(isize,isize),
     Automove,
+    Cast(usize,Coord), //Cast the n-th spell in the player's spellbook at the given tile
+    Descend,            //Take the stairs down, when standing on them
+    Ascend,             //Take the stairs up, when standing on them
+    Open(isize,isize),  //Open the door in the given direction, or pick its lock if it is locked
+    Close(isize,isize), //Close the door in the given direction
+}
+
+/// Size of every level of the dungeon
+pub const LEVEL_SIZE: usize = 42;
+
+/// How many monsters a level is kept stocked with: a base number, and more for every level down
+const MONSTERS_PER_LEVEL: usize = 15;
+const MONSTERS_PER_DEPTH: usize = 5;
+
+/// Percent chance that a creature misses the player at the edge of its sight, each time it acts. In
+/// dimmer light the chance is greater.
+const PLAYER_STEALTH: u32 = 60;
+/// How far the player's lantern shines
+const PLAYER_LIGHT: usize = 5;
+
+/// How far, in steps, the sounds of the player's actions carry. Creatures that hear them come to look.
+const NOISE_WALK: f64 = 3.0;
+const NOISE_FIGHT: f64 = 12.0;
+const NOISE_SPELL: f64 = 8.0;
+
+/// The most places wandering creatures head for at once. Each place needs a map of its own, so once this
+/// many are taken, creatures choose among them.
+const WANDER_GOALS: usize = 4;
+
+/// The most places searching creatures look for the player at once. Once this many are taken, creatures
+/// that lose track of the player search the one of them closest to where the player was.
+const SEARCH_GOALS: usize = 4;
+
+/// A level of the dungeon the player is not on, kept as it was left
+pub struct Level {
+    pub map: Map,
+    pub creatures: Vec<Creature>,
+    pub gold: Vec<(Coord,u32)>,
+}
+
+impl Level {
+    /// A level with nothing on it yet
+    pub fn new(map: Map) -> Level { Level { map: map, creatures: vec![], gold: vec![] } }
 }
 
 pub struct Game {
     player: Creature,
     map: Map,
     creatures: Vec<Creature>,
+    depth: usize,   //The level the player is on, counting from 1
+    levels: Vec<Option<Level>>, //Every level generated so far, by depth. The player's level is kept in map, creatures and gold, so its slot is empty.
+    generator: Option<GeneratorKind>,   //Generator for every new level, or None to vary it with depth
+    seed: u64,      //The seed the game was started with. Replaying it reproduces the run.
+    rng: GameRng,   //All randomness in the game is drawn from here
+    turn: u64,      //Number of turns taken so far
+    time: u64,      //Number of ticks of the game clock so far
+    spells: Vec<SpellDef>,  //Definitions of every spell in the game
+    monsters: Vec<MonsterDef>,  //Definitions of every kind of monster that can be spawned
+    gold: Vec<(Coord,u32)>,     //Piles of gold lying on the floor, and how much is in each
+    cache: MapCache,            //Dijkstra maps shared between creatures and kept between turns
+    view: Visibility,           //What the player sees, out to the sight of the keenest-eyed creature
+    messages: Vec<String>,  //Things that happened, for front-ends to show the player
 }
 
-use dijkstra_map::DijkstraMap;
+use dijkstra_map::{DETOUR_RANGE,DijkstraMap};
 impl Game {
-    pub fn new(w:&PistonWindow) -> Game {
-        Game { 
-            player : Creature::new((1,1),w,"player.png",Behavior::Player), 
-            map: Map::new(w,42),
-            creatures: vec![]
+    pub fn new(seed: u64) -> Game { Game::with_generator(seed, None) }
+
+    /// Start a game whose levels are all made by the given generator. With None, each level uses the
+    /// generator for its depth.
+    pub fn with_generator(seed: u64, generator: Option<GeneratorKind>) -> Game {
+        let mut rng = GameRng::new(seed);
+        let map = Map::level(LEVEL_SIZE, 1, generator.unwrap_or(GeneratorKind::for_depth(1)), &mut rng);
+        let spells = builtin_spells();
+        let mut player = Creature::new((1,1),"player.png",Behavior::Player);
+        player.energy = ACTION_COST;    //The player moves first
+        player.hp = 20;
+        player.max_hp = 20;
+        player.attack = 4;
+        player.mana = 10;
+        player.max_mana = 10;
+        player.sight = VISION_RADIUS;
+        player.stealth = PLAYER_STEALTH;
+        player.light = PLAYER_LIGHT;
+        player.spells = spells.iter().map(|spell| spell.name.clone()).collect();
+        let mut game = Game { 
+            player : player, 
+            map: map,
+            creatures: vec![],
+            depth: 1,
+            levels: vec![None],
+            generator: generator,
+            seed: seed,
+            rng: rng,
+            turn: 0,
+            time: 0,
+            spells: spells,
+            monsters: builtin_monsters(),
+            gold: vec![],
+            cache: MapCache::new(),
+            view: Visibility::default(),
+            messages: vec![],
+        };
+        //Initialize vision
+        game.update_vision();
+        game.scatter_gold();
+        game
+    }
+
+    /// Rebuild a game from the parts of a saved game. `levels` holds every level generated so far, by depth,
+    /// including the one the player is on.
+    #[allow(clippy::too_many_arguments)]
+    pub fn restore(seed: u64, rng: GameRng, turn: u64, time: u64, generator: Option<GeneratorKind>, depth: usize, levels: Vec<Level>, player: Creature) -> Game {
+        let mut levels: Vec<Option<Level>> = levels.into_iter().map(Some).collect();
+        let current = levels[depth-1].take().expect("the player's level is missing");
+        let mut game = Game {
+            player: player,
+            map: current.map,
+            creatures: current.creatures,
+            depth: depth,
+            levels: levels,
+            generator: generator,
+            seed: seed,
+            rng: rng,
+            turn: turn,
+            time: time,
+            spells: builtin_spells(),
+            monsters: builtin_monsters(),
+            gold: current.gold,
+            cache: MapCache::new(),
+            view: Visibility::default(),
+            messages: vec![],
+        };
+        game.update_vision();
+        game
+    }
+
+    ///Functions to retrieve the state front-ends need to draw
+    pub fn player(&self) -> &Creature { &self.player }
+    pub fn map(&self) -> &Map { &self.map }
+    pub fn creatures(&self) -> &Vec<Creature> { &self.creatures }
+    pub fn seed(&self) -> u64 { self.seed }
+    pub fn rng(&self) -> &GameRng { &self.rng }
+    pub fn turn(&self) -> u64 { self.turn }
+    pub fn time(&self) -> u64 { self.time }
+    pub fn messages(&self) -> &[String] { &self.messages }
+    pub fn gold(&self) -> &[(Coord,u32)] { &self.gold }
+    pub fn map_cache(&self) -> &MapCache { &self.cache }
+    pub fn depth(&self) -> usize { self.depth }
+    pub fn generator(&self) -> Option<GeneratorKind> { self.generator }
+    /// Number of levels generated so far
+    pub fn level_count(&self) -> usize { self.levels.len() }
+    /// The level at the given depth, if it has been generated and the player is not on it
+    pub fn level(&self, depth: usize) -> Option<&Level> { self.levels.get(depth.wrapping_sub(1)).and_then(|level| level.as_ref()) }
+
+    /// Whether the run has ended with the player's death
+    pub fn is_over(&self) -> bool { self.player.hp <= 0 }
+
+    /// Replace the built-in spell definitions, for front-ends that read them from disk. The player knows
+    /// every spell there is, so their spellbook is replaced as well.
+    pub fn set_spells(&mut self, spells: Vec<SpellDef>) {
+        self.player.spells = spells.iter().map(|spell| spell.name.clone()).collect();
+        self.spells = spells;
+    }
+    pub fn spells(&self) -> &[SpellDef] { &self.spells }
+
+    /// Replace the built-in monster definitions, for front-ends that read them from disk. Monsters that
+    /// already exist are unaffected; the new definitions are used for every monster spawned afterwards.
+    pub fn set_monsters(&mut self, monsters: Vec<MonsterDef>) { self.monsters = monsters }
+    pub fn monsters(&self) -> &[MonsterDef] { &self.monsters }
+
+    /// The definition of the spell with the given name
+    pub fn spell(&self, name: &str) -> Option<&SpellDef> { self.spells.iter().find(|spell| spell.name == name) }
+
+    /// Coordinates of the creatures the player can see, nearest first
+    pub fn visible_creatures(&self) -> Vec<Coord> {
+        let origin = self.player.coordinates();
+        let mut output: Vec<Coord> = self.creatures.iter().filter(|c| c.object.visible(&self.map)).map(|c| c.coordinates()).collect();
+        output.sort_by_key(|&c| distance(origin, c));
+        output
+    }
+
+    fn message(&mut self, text: String) { self.messages.push(text) }
+
+    fn creature_at(&self, c: Coord) -> Option<usize> { self.creatures.iter().position(|creature| creature.coordinates() == c) }
+
+    /// Spawn a random monster that belongs on the given level, picked according to the monsters' frequencies
+    fn spawn_monster(&mut self, depth: usize) {
+        use rand::Rng;
+        let candidates: Vec<MonsterDef> = self.monsters.iter().filter(|m| m.min_depth <= depth && depth <= m.max_depth && m.frequency > 0).cloned().collect();
+        let total: u32 = candidates.iter().map(|m| m.frequency).sum();
+        if total == 0 { return }
+        let mut roll = self.rng.gen_range(0, total);
+        for monster in candidates {
+            if roll < monster.frequency {
+                self.spawn_creature(&monster);
+                return;
+            }
+            roll -= monster.frequency;
         }
     }
 
-    fn spawn_creature(&mut self, w:&PistonWindow,filename:&str,ai:Behavior) {
-        use rand;
-        use rand::*;
+    /// Place a creature of the given kind on a free tile out of the player's sight, so that monsters are not
+    /// seen appearing from nowhere. Nothing is spawned if there is no such tile.
+    fn spawn_creature(&mut self, monster: &MonsterDef) {
+        if let Some(c) = self.random_tile(|game, c| !game.map.tile(c).visible()) { self.creatures.push( monster.create(c) ) }
+    }
+
+    /// A random passable tile that neither the player nor any creature is standing on, if there is one
+    fn random_floor(&mut self) -> Option<Coord> { self.random_tile(|_,_| true) }
+
+    /// A random passable, unoccupied tile that also passes the given test, if there is one
+    fn random_tile<F: Fn(&Game, Coord) -> bool>(&mut self, fits: F) -> Option<Coord> {
+        use rand::Rng;
+        let occupied = self.occupied(None);
         // Create a list of odd coordinates (even coordinates may have walls)
-        let mut coordinates = vec![];
-        for j in 0..self.map.grid.len() { for i in 0..self.map.grid[0].len() { if i%2==1 && j%2==1 { coordinates.push((i,j)) } } }
+        let mut coordinates: Vec<Coord> = self.map.grid.coords().filter(|&(i,j)| i%2==1 && j%2==1).collect();
         // Shuffle the list
-        rand::thread_rng().shuffle(&mut coordinates);
+        self.rng.shuffle(&mut coordinates);
 
-        //Now go through the list until you find a place to spawn the creature or run out of coordinates.
+        //Now go through the list until you find a fitting tile or run out of coordinates.
         loop {
             match coordinates.pop() {
-                Some((i,j)) => if self.is_passable(i,j) {
-                    self.creatures.push( Creature::new((i,j), w, filename, ai) );
-                    break;
-                },
-                None => break,
+                Some(c) => if self.is_passable(c) && !occupied.contains(&c) && fits(self, c) { return Some(c) },
+                None => return None,
+            }
+        }
+    }
+
+    /// Scatter some gold around the level to be found
+    fn scatter_gold(&mut self) {
+        use rand::Rng;
+        for _ in 0..10 {
+            let amount = self.rng.gen_range(5, 26);
+            if let Some(c) = self.random_floor() { self.drop_gold(c, amount) }
+        }
+    }
+
+    /// Move the player to the level at the given depth, generating it if nobody has been there yet. The
+    /// level being left is kept as it is, to be found the same way on return. The player arrives on the
+    /// stairs leading back.
+    fn change_level(&mut self, depth: usize) {
+        let going_down = depth > self.depth;
+        if self.levels.len() < depth { self.levels.resize_with(depth, || None) }
+        let (next, fresh) = match self.levels[depth-1].take() {
+            Some(level) => (level, false),
+            None => {
+                let generator = self.generator.unwrap_or(GeneratorKind::for_depth(depth));
+                (Level::new(Map::level(LEVEL_SIZE, depth, generator, &mut self.rng)), true)
+            }
+        };
+        let left = Level {
+            map: mem::replace(&mut self.map, next.map),
+            creatures: mem::replace(&mut self.creatures, next.creatures),
+            gold: mem::replace(&mut self.gold, next.gold),
+        };
+        self.levels[self.depth-1] = Some(left);
+        self.depth = depth;
+        // Every map belongs to the level it was built for
+        self.cache.invalidate();
+        if fresh { self.scatter_gold() }
+
+        let stairs = match going_down {
+            true => TerrainType::StairsUp,
+            false => TerrainType::StairsDown,
+        };
+        if let Some(c) = self.map.find(stairs) {
+            // A creature waiting on the stairs is pushed aside
+            if let Some(n) = self.creature_at(c) {
+                if let Some(free) = self.free_tile_near(c) { self.creatures[n].object.move_to(free) }
             }
+            self.player.object.move_to(c);
         }
+        self.message(match going_down {
+            true => format!("You descend to level {}.", depth),
+            false => format!("You climb up to level {}.", depth),
+        });
+    }
+
+    /// Take the stairs the player is standing on, if they lead the given way. Returns whether they did.
+    fn take_stairs(&mut self, down: bool) -> bool {
+        let on = self.map.tile(self.player.coordinates()).terrain().clone();
+        match (on, down) {
+            (TerrainType::StairsDown, true) => self.change_level(self.depth + 1),
+            (TerrainType::StairsUp, false) => self.change_level(self.depth - 1),
+            (_, true) => {
+                self.message("There are no stairs down here.".to_string());
+                return false;
+            }
+            (_, false) => {
+                self.message("There are no stairs up here.".to_string());
+                return false;
+            }
+        }
+        true
     }
 
-    // Function to retrive coordinates of non-terrain obstacles in the dungeon
-    fn get_obstacles(&self) -> Vec<(usize,usize)> {
-        let mut output = vec![];
-        output.push((self.player.object.i,self.player.object.j));
-        for n in 0..self.creatures.len() { output.push((self.creatures[n].object.i,self.creatures[n].object.j))}
+    /// Add gold to the floor, on top of any pile already there
+    fn drop_gold(&mut self, c: Coord, amount: u32) {
+        match self.gold.iter().position(|&(pile,_)| pile == c) {
+            Some(n) => self.gold[n].1 += amount,
+            None => self.gold.push((c, amount)),
+        }
+    }
+
+    /// Pick up the gold at the given tile, if there is any
+    fn take_gold(&mut self, c: Coord) -> Option<u32> {
+        let n = self.gold.iter().position(|&(pile,_)| pile == c)?;
+        Some(self.gold.remove(n).1)
+    }
+
+    /// The tiles held by the player and the creatures, leaving out the n-th creature if one is given. The
+    /// creature is left out by its place in the list rather than by its tile, so that a creature sharing its
+    /// tile would still be in the way. Creatures act one at a time and this is gathered afresh for each move,
+    /// so a tile taken earlier in the same turn is seen as taken.
+    fn occupied(&self, except: Option<usize>) -> HashSet<Coord> {
+        let mut output: HashSet<Coord> = self.creatures.iter().enumerate()
+            .filter(|&(m,_)| Some(m) != except)
+            .map(|(_,creature)| creature.coordinates()).collect();
+        output.insert(self.player.coordinates());
         output
     }
 
-    // Function to determine if the tile at the given coordinates is passable
-    fn is_passable(&mut self, i:usize,j:usize) -> bool {
-        self.map.tile(i,j).is_passable()
-        /*match self.get_creature(i,j) {
+    /// Whether every creature, the player included, has a tile to itself
+    fn is_unstacked(&self) -> bool {
+        let mut tiles = HashSet::new();
+        tiles.insert(self.player.coordinates());
+        self.creatures.iter().all(|creature| tiles.insert(creature.coordinates()))
+    }
+
+    /// The free tile that can be walked to from c in the fewest steps, other than c itself. Doors are
+    /// walked through but never chosen. Returns None if every tile within reach is taken.
+    fn free_tile_near(&self, c: Coord) -> Option<Coord> {
+        let occupied: HashSet<Coord> = self.creatures.iter().map(|creature| creature.coordinates()).collect();
+        let mut seen = HashSet::new();
+        seen.insert(c);
+        let mut queue = VecDeque::new();
+        queue.push_back(c);
+        while let Some(here) = queue.pop_front() {
+            if here != c && self.is_passable(here) && !occupied.contains(&here) { return Some(here) }
+            for next in self.map.grid.neighbours(here) {
+                if self.map.tile(next).is_passable() && seen.insert(next) { queue.push_back(next) }
+            }
+        }
+        None
+    }
+
+    // Function to determine if the tile at the given coordinates can be stepped onto without opening a door
+    fn is_passable(&self, c: Coord) -> bool {
+        self.map.tile(c).is_passable() && !self.map.tile(c).terrain().is_closed()
+        /*match self.get_creature(c) {
             Some(_) => false,
-            None => self.map.tile(i,j).is_passable(),
+            None => self.map.tile(c).is_passable(),
         }*/
     }
 
-    fn get_dijkstra_map(&self, goals: Vec<(usize,usize)>) -> DijkstraMap {
-        use dijkstra_map::DijkstraTile;
-        //Create a map of dijkstra tiles, ignoring the locations of creatures
-        let mut map: Vec<Vec<DijkstraTile>> = self.map.grid.iter().map(
-            |row| row.iter().map(
-                |tile| match tile.is_passable() {
-                    true => DijkstraTile::Passable,
-                    false => DijkstraTile::Impassable,
-                }
-            ).collect()
-        ).collect();
-        //Add the goals. Only goals on passable ground are added.
-        for (i,j) in goals {
-            match map[j][i] {
-                DijkstraTile::Passable => map[j][i] = DijkstraTile::Goal,
-                _ => (),
+    /// The closed doors within DETOUR_RANGE of c, which creatures that cannot open them treat as blocked. A
+    /// creature looking for a way around never looks further than that.
+    fn closed_doors_near(&self, c: Coord) -> HashSet<Coord> {
+        let (left, top) = (c.0.saturating_sub(DETOUR_RANGE), c.1.saturating_sub(DETOUR_RANGE));
+        let (right, bottom) = ((c.0 + DETOUR_RANGE).min(self.map.width() - 1), (c.1 + DETOUR_RANGE).min(self.map.height() - 1));
+        (top..bottom+1).flat_map(|j| (left..right+1).map(move |i| (i,j)))
+            .filter(|&c| self.map.tile(c).terrain().is_closed()).collect()
+    }
+
+    /// Change the terrain of the tile at c, for doors, spells and creatures that reshape the dungeon. What
+    /// depends on the terrain follows: the dijkstra maps only change at that tile, so they are updated in
+    /// place rather than rebuilt, and the light and the player's view are worked out again. Front-ends draw
+    /// tiles from their terrain, so they need not be told. Returns false, changing nothing, if c is outside
+    /// the level, or if someone stands or gold lies there and the new terrain could not hold them.
+    pub fn set_terrain(&mut self, c: Coord, terrain: TerrainType) -> bool {
+        let taken = c == self.player.coordinates() || self.creature_at(c).is_some() || self.gold.iter().any(|&(pile,_)| pile == c);
+        let holds = terrain.is_passable() && !terrain.is_closed();
+        if (taken && !holds) || self.map.set_terrain(c, terrain).is_none() { return false }
+        self.cache.set_cost(c, self.map.tile(c).map_cost());
+        self.update_vision();
+        true
+    }
+
+    /// The player opens the door at c, or picks its lock if it is locked, which leaves it closed. Returns
+    /// whether they did either.
+    fn open_door(&mut self, c: Coord) -> bool {
+        let text = match *self.map.tile(c).terrain() {
+            TerrainType::Door => {
+                self.set_terrain(c, TerrainType::OpenDoor);
+                "You open the door."
+            }
+            TerrainType::LockedDoor => {
+                self.set_terrain(c, TerrainType::Door);
+                "You pick the lock."
+            }
+            TerrainType::OpenDoor => {
+                self.message("The door is already open.".to_string());
+                return false;
+            }
+            _ => {
+                self.message("There is no door there.".to_string());
+                return false;
             }
         };
-        //Construct and return the dijkstra map
-        DijkstraMap::new(&map)
+        self.message(text.to_string());
+        true
     }
 
-    pub fn on_load(&mut self)  {
-        //Initialize vision
-        self.map.update_vision((self.player.object.i, self.player.object.j));        
+    /// The player closes the door at c. Nothing may be standing or lying in the doorway. Returns whether they
+    /// did.
+    fn close_door(&mut self, c: Coord) -> bool {
+        let text = match *self.map.tile(c).terrain() {
+            TerrainType::OpenDoor => match self.set_terrain(c, TerrainType::Door) {
+                true => {
+                    self.message("You close the door.".to_string());
+                    return true;
+                }
+                false => "Something is in the way.",
+            },
+            TerrainType::Door | TerrainType::LockedDoor => "The door is already closed.",
+            _ => "There is no door there.",
+        };
+        self.message(text.to_string());
+        false
     }
 
-    pub fn on_draw(&mut self, ren: RenderArgs, e: PistonWindow) {//, glyphs: &mut Glyphs) {
-        e.draw_2d(|c, g| {
-            clear([0.0, 0.0, 0.0, 1.0], g);
-            let view = c.transform.trans((ren.width / 2) as f64-self.player.object.x(),(ren.height / 2) as f64-self.player.object.y());
+    /// Recompute the light, and what the player sees by it. The view reaches as far as any creature can see,
+    /// not just as far as the player can: vision is symmetric, so it also tells which creatures can see the
+    /// player.
+    fn update_vision(&mut self) {
+        let lamps: Vec<(Coord,usize)> = Some(&self.player).into_iter().chain(self.creatures.iter())
+            .filter(|c| c.light > 0).map(|c| (c.coordinates(), c.light)).collect();
+        self.map.update_light(&lamps);
+        let sight = self.creatures.iter().map(|c| c.sight).chain(self.monsters.iter().map(|m| m.sight)).fold(self.player.sight, usize::max);
+        self.view = self.map.visibility(self.player.coordinates(), sight);
+        self.map.update_vision(&self.view, self.player.sight);
+    }
 
-            // render the map
-            self.map.render(g, view);
-            //render the player
-            self.player.object.render(g, view, &self.map);
+    /// Creatures within the given number of steps of the player hear them, and those that did not know
+    /// where the player was go to look. Sound goes around walls, so it is measured along the ground.
+    fn hear(&mut self, noise: f64) {
+        let origin = self.player.coordinates();
+        let target = self.search_goal(origin);
+        let heard = self.cache.get(&self.map, "player", vec![origin]);
+        for creature in self.creatures.iter_mut() {
+            let near = heard.value(creature.coordinates()).is_some_and(|steps| steps <= noise);
+            if near && creature.awareness != Awareness::Aware { creature.awareness = Awareness::Searching(target) }
+        }
+    }
 
-            //render other creatures
-            for creature in self.creatures.iter() {
-                creature.object.render(g,view,&self.map);
-            }
-        });
+    /// Where to look for a player last known to be at c: c itself, or the closest place other creatures are
+    /// already searching if there are enough of those
+    fn search_goal(&self, c: Coord) -> Coord {
+        let mut goals: Vec<Coord> = self.creatures.iter().filter_map(|creature| match creature.awareness {
+            Awareness::Searching(goal) => Some(goal),
+            _ => None,
+        }).collect();
+        goals.sort();
+        goals.dedup();
+        match goals.len() >= SEARCH_GOALS && !goals.contains(&c) {
+            true => goals.into_iter().min_by_key(|&goal| distance(goal, c)).unwrap_or(c),
+            false => c,
+        }
     }
-    pub fn on_input(&mut self, inp: &Input, w: &PistonWindow) {
-        let mut command = Command::None;
-        match inp {
-            &Input::Press(key) => {
-                match key {
-                    //Arrow keys
-                    Button::Keyboard(Key::Up) => command = Command::Move(0,-1),
-                    Button::Keyboard(Key::Down) => command = Command::Move(0,1),
-                    Button::Keyboard(Key::Left) => command = Command::Move(-1,0),
-                    Button::Keyboard(Key::Right) => command = Command::Move(1,0),
-                    //Numpad keys
-                    Button::Keyboard(Key::NumPad1) => command = Command::Move(-1,1),
-                    Button::Keyboard(Key::NumPad2) => command = Command::Move(0,1),
-                    Button::Keyboard(Key::NumPad3) => command = Command::Move(1,1),
-                    Button::Keyboard(Key::NumPad4) => command = Command::Move(-1,-0),
-                    Button::Keyboard(Key::NumPad5) => command = Command::Move(0,0),
-                    Button::Keyboard(Key::NumPad6) => command = Command::Move(1,0),
-                    Button::Keyboard(Key::NumPad7) => command = Command::Move(-1,-1),
-                    Button::Keyboard(Key::NumPad8) => command = Command::Move(0,-1),
-                    Button::Keyboard(Key::NumPad9) => command = Command::Move(1,-1),
-                    //Automated movement
-                    Button::Keyboard(Key::O) => command = Command::Automove,
-                    _ => {}
+
+    /// The n-th creature looks for the player. Vision is symmetric, so it sees the player exactly when the
+    /// player's view, cut down to the creature's sight, takes in its tile, and the player stands in light.
+    fn look_for_player(&mut self, n: usize) {
+        use rand::Rng;
+        let position = self.creatures[n].coordinates();
+        let sight = self.creatures[n].sight;
+        let light = self.map.tile(self.player.coordinates()).light();
+        match (self.creatures[n].awareness, light > 0 && self.view.within(position, sight)) {
+            (Awareness::Aware, true) => (),
+            // Out of sight, the player is looked for where they went. They only move between the creatures'
+            // turns, so that is where they are now.
+            (Awareness::Aware, false) => self.creatures[n].awareness = Awareness::Searching(self.search_goal(self.player.coordinates())),
+            // A creature that reaches the place it was searching and finds nobody gives up
+            (Awareness::Searching(c), false) => if c == position { self.creatures[n].awareness = Awareness::Unaware },
+            (Awareness::Unaware, false) => (),
+            (awareness, true) => {
+                // Searching creatures are on the lookout. Others may miss a stealthy player, and the further
+                // away and the more poorly lit the player is, the more likely they are to.
+                let missed = match awareness {
+                    Awareness::Searching(_) => 0,
+                    _ => self.player.stealth as usize * distance(position, self.player.coordinates()) * LIGHT_FULL as usize / (sight * light as usize),
+                };
+                if self.rng.gen_range(0, 100) >= missed {
+                    self.creatures[n].awareness = Awareness::Aware;
+                    // Only say so the first time: a creature searching for the player already knew of them
+                    if awareness == Awareness::Unaware && self.creatures[n].object.visible(&self.map) {
+                        let name = self.creatures[n].name.clone();
+                        self.message(format!("The {} notices you!", name));
+                    }
                 }
             }
-            _ => {}
         }
+    }
+
+    /// Advance the game by one player command. Returns whether the player acted (and so a turn passed).
+    pub fn step(&mut self, command: Command) -> bool {
+        // The dead take no more turns
+        if self.is_over() { return false }
         //Use a bool to check whether the player did anything
         let mut player_acted = true;
+        //Most actions make no more noise than footsteps
+        let mut noise = NOISE_WALK;
         //Handle player action
         match command {
             // Attempt to move in given direction
             Command::Move(i,j) => {
-                let (i0,j0) = self.player.coordinates();
-                let (i1,j1) = ((i0 as isize + i) as usize, (j0 as isize + j) as usize);
-                // Stop if there are any obstacles in the way
-                let mut exists_obstacle = false;
-                for (i2,j2) in self.get_obstacles() { if i2==i1 && j2==j1 { exists_obstacle=true; break; } }
-                // If the terrain is passable, move there
-                if self.is_passable(i1,j1) && !exists_obstacle { self.player.object.mov(i,j) };
+                // Moving off the edge of the map goes nowhere
+                if let Some(c) = self.map.grid.offset(self.player.coordinates(), i, j) {
+                    // Moving into a creature attacks it
+                    if let Some(n) = self.creature_at(c) {
+                        self.player_attacks(n);
+                        noise = NOISE_FIGHT;
+                    } else if self.map.tile(c).terrain().is_closed() {
+                        // Moving into a closed door opens it
+                        self.open_door(c);
+                    } else {
+                        // If the terrain is passable, move there
+                        if self.is_passable(c) { self.player.object.move_to(c) };
+                    }
+                }
             },
             Command::Automove => {
                 // Autoexplore the map by pressing "o"
-                let mut goals = vec![]; //Goals will be all the unexplored tiles on the map
-                for j in 0..self.map.grid.len() { for i in 0..self.map.grid[0].len() { if !self.map.tile(i,j).is_explored() { goals.push((i,j)) } } }
-                let unexploredmap = self.get_dijkstra_map(goals).with_obstacles(self.get_obstacles());
-                self.player.object.automove(&unexploredmap);
+                //Goals will be all the unexplored tiles on the map
+                let goals = self.map.grid.coords().filter(|&c| !self.map.tile(c).is_explored()).collect();
+                let origin = self.player.coordinates();
+                let occupied = self.occupied(None);
+                let step = self.cache.get(&self.map, "unexplored", goals).get_next_step_around(origin, &occupied);
+                // Doors on the way are opened rather than walked into. The maps lead around locked ones.
+                match self.map.tile(step).terrain().is_closed() {
+                    true => { self.open_door(step); }
+                    false => self.player.object.move_to(step),
+                }
+            },
+            Command::Cast(n,target) => {
+                player_acted = self.cast(n, target);
+                noise = NOISE_SPELL;
+            },
+            Command::Descend => player_acted = self.take_stairs(true),
+            Command::Ascend => player_acted = self.take_stairs(false),
+            Command::Open(i,j) => player_acted = match self.map.grid.offset(self.player.coordinates(), i, j) {
+                Some(c) => self.open_door(c),
+                None => false,
+            },
+            Command::Close(i,j) => player_acted = match self.map.grid.offset(self.player.coordinates(), i, j) {
+                Some(c) => self.close_door(c),
+                None => false,
             },
             _ => player_acted = false,
         };
 
         if player_acted {
+            // The player picks up any gold they end up standing on
+            if let Some(amount) = self.take_gold(self.player.coordinates()) {
+                self.player.gold += amount;
+                self.message(format!("You pick up {} gold.", amount));
+            }
+            self.turn += 1;
+            self.player.spend(1.0);
+            // The player slowly recovers mana
+            if self.player.mana < self.player.max_mana { self.player.mana += 1 }
             // First, recompute vision
-            self.map.update_vision((self.player.object.i, self.player.object.j));
+            self.update_vision();
+            self.hear(noise);
 
-            // If there are fewer than 8 monsters, spawn a new one
-            if self.creatures.len() < 20 { self.spawn_creature(w,"nyancat.png",Behavior::Coward) };
+            // Keep the level stocked with monsters. Deeper levels hold more of them.
+            if self.creatures.len() < MONSTERS_PER_LEVEL + self.depth * MONSTERS_PER_DEPTH { self.spawn_monster(self.depth) };
 
-            // Compute a dijkstramap containing the location of the player. We only need to do this once.
-            let player_location = self.get_dijkstra_map(vec![(self.player.object.i,self.player.object.j)]);
+            self.run_until_player_turn();
+            self.cache.end_turn();
+            debug_assert!(self.is_unstacked(), "two creatures share a tile on turn {}", self.turn);
+        }
+        player_acted
+    }
 
-            // Compute a dijkstramap with all the tiles the player cannot see. Again, we only need to do this once.
-            let mut goals = vec![];
-            for j in 0..self.map.grid.len() {
-                for i in 0..self.map.grid[0].len() {
-                    match los(&self.map,self.player.object.i as isize,self.player.object.j as isize,i as isize,j as isize) {
-                        Some((x,y)) if x==i && y==j => (),
-                        _ => goals.push((i,j)),
-                    }
-                }
-            }
-            let unseen_tiles = self.map.get_dijkstra_map(goals);
+    /// Let the game clock run until the player has enough energy to act again. Creatures act whenever they
+    /// have stored up enough energy, so fast creatures act several times between the player's actions and
+    /// slow ones only every few. This does not depend on input, so it works the same headless.
+    fn run_until_player_turn(&mut self) {
+        // The player does not move until the loop ends, so the maps that depend on them only need to be
+        // computed once.
+        let mut maps = self.desire_maps();
+
+        while !self.player.can_act() {
+            self.time += 1;
+            self.player.tick();
             // Handle monster actions
             // I had difficulty here because I was iterating on self.creatures, but this was causing an error inside the loop because I had already borrowed creatures as immutable and was trying to borrow it again as mutable. I solved this by iterating over indices and only accessing a creture when absolutely necessary.
-            for n in 0..self.creatures.len() {//creature in self.creatures.iter_mut() {
-                // Nyancats are cowardly, so they run away from the player and toward unseen tiles
-                // We will take the original maps (which do not change) and update them with obstacles.
-                // This is MUCH faster than creating a new map from scratch each time.
-                let dmap = &(&player_location*(-1.0)+&unseen_tiles*(0.5)).with_obstacles(self.get_obstacles());
-                self.creatures[n].object.automove(&dmap);
+            // Creatures act in the order they were spawned, and each moves before the next one chooses its
+            // step. When two want the same tile, the one that acts first takes it and the other goes around or
+            // waits, so the outcome never depends on anything but the game state.
+            for n in 0..self.creatures.len() {
+                self.creatures[n].tick();
+                while self.creatures[n].can_act() {
+                    self.creature_acts(n, &mut maps);
+                    if self.creatures[n].fear > 0 { self.creatures[n].fear -= 1 }
+                    self.creatures[n].spend(1.0);
+                    // Nothing else happens once the player is dead
+                    if self.is_over() { return }
+                }
+            }
+        }
+    }
+
+    /// Gather the maps the creatures on the level may desire, building only those that changed since the
+    /// last turn. Maps no creature desires are left out, so a level of creatures that only hunt the player
+    /// costs one map however many of them there are.
+    fn desire_maps(&mut self) -> DesireMaps {
+        let (map, cache) = (&self.map, &mut self.cache);
+        let origin = self.player.coordinates();
+        let wanted: HashSet<&str> = self.creatures.iter().flat_map(ai::desired).collect();
+        let mut maps = HashMap::new();
+
+        // Compute a dijkstramap containing the location of the player. Fleeing creatures run away from the
+        // player, so compute the map for that as well.
+        if wanted.contains("player") || wanted.contains("safety") {
+            let player_location = cache.get(map, "player", vec![origin]).clone();
+            if wanted.contains("safety") {
+                maps.insert("safety", cache.get_or_build("safety", vec![origin], || player_location.flee()).clone());
+            }
+            maps.insert("player", player_location);
+        }
+
+        // Compute a dijkstramap with all the tiles the player cannot see, whether out of sight or in the dark.
+        // Vision was updated before the creatures' turn, so the tiles' own flags say which those are. They
+        // change with the light as well as with where the player stands, so the map is kept by its goals.
+        if wanted.contains("unseen") {
+            let unseen = map.grid.coords().filter(|&c| !map.tile(c).visible()).collect();
+            maps.insert("unseen", cache.get(map, "unseen", unseen).clone());
+        }
+
+        if wanted.contains("items") {
+            maps.insert("items", cache.get(map, "items", self.gold.iter().map(|&(c,_)| c).collect()).clone());
+        }
+        if wanted.contains("exits") {
+            let exits = map.grid.coords().filter(|&c| {
+                let terrain = map.tile(c).terrain();
+                terrain.is_door() || matches!(*terrain, TerrainType::StairsUp | TerrainType::StairsDown)
+            }).collect();
+            maps.insert("exits", cache.get(map, "exits", exits).clone());
+        }
+
+        // Each kind of creature that desires its own kind is drawn to it
+        let mut allies = HashMap::new();
+        for creature in self.creatures.iter() {
+            if !allies.contains_key(&creature.name) && ai::desired(creature).contains(&"allies") {
+                let goals = self.creatures.iter().filter(|c| c.name == creature.name).map(|c| c.coordinates()).collect();
+                allies.insert(creature.name.clone(), cache.get(map, &format!("allies {}", creature.name), goals).clone());
+            }
+        }
+
+        DesireMaps::new(maps, allies)
+    }
+
+    // Dijkstra map of the gold lying on the floor
+    fn items_map(&mut self) -> DijkstraMap {
+        let goals = self.gold.iter().map(|&(c,_)| c).collect();
+        self.cache.get(&self.map, "items", goals).clone()
+    }
+
+    /// The n-th creature takes one action
+    fn creature_acts(&mut self, n: usize, maps: &mut DesireMaps) {
+        let position = self.creatures[n].coordinates();
+        let adjacent = distance(position, self.player.coordinates()) == 1;
+        let allies: Vec<Coord> = self.creatures.iter().enumerate()
+            .filter(|&(m,c)| m != n && c.name == self.creatures[n].name)
+            .map(|(_,c)| c.coordinates()).collect();
+        self.look_for_player(n);
+        let mood = ai::mood(&self.creatures[n], &allies);
+        let hunting = matches!(mood, Mood::Hunt | Mood::Regroup);
+
+        match self.creatures[n].ai {
+            // Creatures that know where the player is and are not fleeing act on them when next to them
+            Behavior::Thief if hunting && adjacent => self.steal(n),
+            Behavior::Hunter | Behavior::Wanderer | Behavior::Pack if hunting && adjacent => self.creature_attacks_player(n),
+            // Otherwise creatures move along the maps they desire
+            _ => self.creature_moves(n, mood, maps),
+        }
+    }
+
+    /// The n-th creature moves down the sum of the maps it desires in its current mood
+    fn creature_moves(&mut self, n: usize, mood: Mood, maps: &mut DesireMaps) {
+        let position = self.creatures[n].coordinates();
+
+        // Idle creatures, and wanderers even on the hunt, also head for a place of their own choosing, and
+        // choose another once they get there. Searching creatures head for where the player was.
+        let wandering = mood == Mood::Idle || (self.creatures[n].ai == Behavior::Wanderer && mood == Mood::Hunt);
+        if wandering && self.creatures[n].goal.is_none_or(|goal| goal == position) { self.creatures[n].goal = self.wander_goal(n) }
+        let goal = match (mood, self.creatures[n].awareness) {
+            (Mood::Search, Awareness::Searching(c)) => Some(c),
+            _ if wandering => self.creatures[n].goal,
+            _ => None,
+        };
+        let towards = match goal {
+            Some(goal) => Some((goal, self.cache.get(&self.map, &format!("goal {} {}", goal.0, goal.1), vec![goal]))),
+            None => None,
+        };
+        // The maps ignore creatures, so they are shared by all of them. Creatures in the way are only
+        // accounted for when choosing the step.
+        let dmap = maps.combined(&ai::desires(&self.creatures[n], mood), &self.creatures[n].name, mood, towards);
+
+        if let Some(dmap) = dmap {
+            let mut occupied = self.occupied(Some(n));
+            // Creatures that cannot open doors go around closed ones, or wait behind them, as they would
+            // behind another creature
+            if !self.creatures[n].opens_doors && !self.creatures[n].breaks_doors { occupied.extend(self.closed_doors_near(position)) }
+            let mut step = dmap.get_next_step_around(position, &occupied);
+            // The maps lead around locked doors, so creatures that break doors look for one in their way
+            if self.creatures[n].breaks_doors {
+                if let Some(door) = self.locked_door_ahead(dmap, position) { step = door }
+            }
+            match *self.map.tile(step).terrain() {
+                TerrainType::Door => {
+                    self.creature_opens_door(n, step);
+                    // The maps the creatures share have changed with the door
+                    *maps = self.desire_maps();
+                }
+                TerrainType::LockedDoor if self.creatures[n].breaks_doors => {
+                    self.creature_opens_door(n, step);
+                    *maps = self.desire_maps();
+                }
+                // Otherwise only the player gets through locked doors
+                TerrainType::LockedDoor => (),
+                _ => self.creatures[n].object.move_to(step),
+            }
+        }
+
+        // Thieves pick up any gold they walk over
+        if self.creatures[n].ai == Behavior::Thief {
+            if let Some(amount) = self.take_gold(self.creatures[n].coordinates()) {
+                self.creatures[n].gold += amount;
+                if maps.has("items") {
+                    let items = self.items_map();
+                    maps.set_items(items);
+                }
+                if self.creatures[n].object.visible(&self.map) {
+                    let name = self.creatures[n].name.clone();
+                    self.message(format!("The {} picks up some gold.", name));
+                }
+            }
+        }
+    }
+
+    /// A new place for the n-th creature to wander to, somewhere another creature is already headed if
+    /// there are enough of those
+    fn wander_goal(&mut self, n: usize) -> Option<Coord> {
+        use rand::Rng;
+        let position = self.creatures[n].coordinates();
+        let mut goals: Vec<Coord> = self.creatures.iter().filter_map(|creature| creature.goal).filter(|&goal| goal != position).collect();
+        goals.sort();
+        goals.dedup();
+        match goals.len() >= WANDER_GOALS {
+            true => Some(goals[self.rng.gen_range(0, goals.len())]),
+            false => self.random_floor(),
+        }
+    }
+
+    /// A locked door next to `from` with a tile on its far side that is closer to where the map leads than
+    /// `from` is, counting the two steps through the door. Tiles the map does not reach count as furthest of all.
+    fn locked_door_ahead(&self, dmap: &DijkstraMap, from: Coord) -> Option<Coord> {
+        let here = dmap.value(from).unwrap_or(f64::INFINITY);
+        self.map.grid.neighbours(from)
+            .filter(|&door| *self.map.tile(door).terrain() == TerrainType::LockedDoor)
+            .find(|&door| self.map.grid.neighbours(door).any(|beyond| {
+                distance(from, beyond) == 2 && dmap.value(beyond).is_some_and(|v| v + 2.0 < here)
+            }))
+    }
+
+    /// The n-th creature opens the closed door at c, or smashes it if it cannot open doors or the door is
+    /// locked, taking its action
+    fn creature_opens_door(&mut self, n: usize, c: Coord) {
+        let locked = *self.map.tile(c).terrain() == TerrainType::LockedDoor;
+        let (terrain, text) = match self.creatures[n].opens_doors && !locked {
+            true => (TerrainType::OpenDoor, "opens a door"),
+            false => (TerrainType::Floor, "smashes a door to pieces"),
+        };
+        self.set_terrain(c, terrain);
+        if self.creatures[n].object.visible(&self.map) || self.map.tile(c).visible() {
+            let name = self.creatures[n].name.clone();
+            self.message(format!("The {} {}.", name, text));
+        }
+    }
+
+    /// The n-th creature tries to rob the player
+    fn steal(&mut self, n: usize) {
+        let name = self.creatures[n].name.clone();
+        if self.player.gold == 0 {
+            // With nothing to take, the thief gives up for a while
+            self.creatures[n].fear = 10;
+            self.message(format!("The {} finds nothing to steal.", name));
+        } else {
+            let amount = self.player.gold.div_ceil(2);
+            self.player.gold -= amount;
+            self.creatures[n].gold += amount;
+            self.message(format!("The {} steals {} gold!", name, amount));
+        }
+    }
+
+    /// Cast the n-th spell of the player's spellbook at the given target. Returns whether the spell was
+    /// cast; if it could not be, no time passes and the reason is added to the messages.
+    fn cast(&mut self, n: usize, target: Coord) -> bool {
+        let spell = match self.player.spells.get(n).and_then(|name| self.spell(name)) {
+            Some(spell) => spell.clone(),
+            None => {
+                self.message("You don't know that spell.".to_string());
+                return false;
             }
+        };
+        if self.player.mana < spell.cost {
+            self.message(format!("You need {} mana to cast {}.", spell.cost, spell.name));
+            return false;
+        }
+        let origin = self.player.coordinates();
+        let target = match spell.target {
+            Targeting::Caster => origin,
+            _ => target,
+        };
+        // The target must be in range and in sight
+        if distance(origin, target) > spell.range || !self.map.grid.contains(target) || !self.map.tile(target).visible() {
+            self.message(format!("That is out of range of {}.", spell.name));
+            return false;
         }
+        match (spell.target, self.creature_at(target)) {
+            (Targeting::Creature, None) => {
+                self.message(format!("{} must be aimed at a creature.", spell.name));
+                return false;
+            }
+            (Targeting::Tile, Some(_)) => {
+                self.message("Something is in the way.".to_string());
+                return false;
+            }
+            (Targeting::Tile, None) if !self.is_passable(target) => {
+                self.message(format!("{} must be aimed at open ground.", spell.name));
+                return false;
+            }
+            _ => (),
+        }
+
+        self.player.mana -= spell.cost;
+        self.message(format!("You cast {}.", spell.name));
+        // A creature caught by a spell knows where it came from
+        if let Some(n) = self.creature_at(target) { self.creatures[n].awareness = Awareness::Aware }
+        for effect in spell.effects.iter() { self.apply_effect(*effect, target) }
+        true
     }
-}
\ No newline at end of file
+
+    /// Damage done by one blow: a roll of up to the attacker's attack, less the defender's defense
+    fn roll_damage(&mut self, attack: i32, defense: i32) -> i32 {
+        use rand::Rng;
+        let roll = self.rng.gen_range(1, attack.max(1) + 1);
+        (roll - defense).max(0)
+    }
+
+    /// The player strikes the n-th creature
+    fn player_attacks(&mut self, n: usize) {
+        self.creatures[n].awareness = Awareness::Aware;
+        let damage = self.roll_damage(self.player.attack, self.creatures[n].defense);
+        let name = self.creatures[n].name.clone();
+        match damage {
+            0 => self.message(format!("You miss the {}.", name)),
+            _ => self.message(format!("You hit the {} for {}.", name, damage)),
+        }
+        self.hurt_creature(n, damage);
+    }
+
+    /// The n-th creature strikes the player
+    fn creature_attacks_player(&mut self, n: usize) {
+        let damage = self.roll_damage(self.creatures[n].attack, self.player.defense);
+        let name = self.creatures[n].name.clone();
+        match damage {
+            0 => self.message(format!("The {} misses you.", name)),
+            _ => self.message(format!("The {} hits you for {}.", name, damage)),
+        }
+        self.player.hp -= damage;
+        if self.is_over() { self.message(format!("You were killed by a {}.", name)) }
+    }
+
+    /// Take hit points from the n-th creature, removing it from the game if it dies
+    fn hurt_creature(&mut self, n: usize, amount: i32) {
+        self.creatures[n].hp -= amount;
+        if self.creatures[n].hp <= 0 {
+            let creature = self.creatures.remove(n);
+            self.message(format!("The {} dies.", creature.name));
+            // Whatever it was carrying falls to the floor
+            if creature.gold > 0 {
+                self.drop_gold(creature.coordinates(), creature.gold);
+                self.message(format!("It drops {} gold.", creature.gold));
+            }
+        }
+    }
+
+    /// Apply one effect of a spell cast by the player at the given target
+    fn apply_effect(&mut self, effect: Effect, target: Coord) {
+        match effect {
+            Effect::Damage(amount) => if let Some(n) = self.creature_at(target) { self.hurt_creature(n, i32::try_from(amount).unwrap_or(i32::MAX)) },
+            Effect::Teleport => {
+                self.player.object.move_to(target);
+                self.update_vision();
+            },
+            Effect::Light(radius) => {
+                for c in self.map.light_area(target, radius) { self.map.tile_mut(c).explore() }
+                self.update_vision();
+            },
+            Effect::Fear(actions) => if let Some(n) = self.creature_at(target) {
+                self.creatures[n].fear = actions;
+                let name = self.creatures[n].name.clone();
+                self.message(format!("The {} panics!", name));
+            },
+            Effect::Dig | Effect::Shatter | Effect::Burn => {
+                // Each works on its own kind of terrain, and leaves floor behind
+                let text = match (effect, self.map.tile(target).terrain()) {
+                    (Effect::Dig, &TerrainType::Wall) | (Effect::Dig, &TerrainType::Torch) if !self.map.grid.on_edge(target) => "The wall crumbles to dust.",
+                    (Effect::Shatter, &TerrainType::Window) => "The window shatters.",
+                    (Effect::Burn, terrain) if terrain.is_door() => "The door burns away.",
+                    _ => return,
+                };
+                if self.set_terrain(target, TerrainType::Floor) { self.message(text.to_string()) }
+            },
+        }
+    }
+}
+
+/// Number of steps between two tiles, counting diagonal steps as one
+pub fn distance(a: Coord, b: Coord) -> usize {
+    a.0.abs_diff(b.0).max(a.1.abs_diff(b.1))
+}
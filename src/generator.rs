@@ -0,0 +1,598 @@
+//! Level generators. Each one fills a grid with terrain in its own style; `GeneratorKind` names them so
+//! that a game can pick one per level, and so that the choice can be stored in saves and replays.
+//!
+//! Every generator makes the tile at START floor and leaves every passable tile reachable from it. Pockets
+//! that come out cut off are joined to the rest by `connect`, and `is_connected` checks the result with a
+//! dijkstra flood from START. Last, torches are hung on some of the walls to light the level, and doors are
+//! left open, closed or locked.
+
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use dijkstra_map::{DijkstraMap,DijkstraTile};
+use dungeon::TerrainType;
+use grid::{Coord,Grid};
+use rand::Rng;
+
+/// The tile every level is guaranteed to reach from. The player starts the game here.
+pub const START: Coord = (1,1);
+
+pub trait Generator {
+	/// Fill a grid of the given size with terrain. The outer edge is always wall.
+	fn generate<R: Rng>(&self, width: usize, height: usize, rng: &mut R) -> Grid<TerrainType>;
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum GeneratorKind {
+	RecursiveSplit,	//Rooms made by splitting the level with walls, joined by doors
+	Caves,			//Caverns grown by a cellular automaton
+	Rooms,			//Rectangular rooms joined by corridors
+	DrunkardsWalk,	//Winding tunnels dug by a random walk
+}
+
+impl GeneratorKind {
+	pub fn all() -> [GeneratorKind;4] {
+		[GeneratorKind::RecursiveSplit, GeneratorKind::Caves, GeneratorKind::Rooms, GeneratorKind::DrunkardsWalk]
+	}
+
+	/// The name used on the command line and in save files
+	pub fn name(&self) -> &'static str {
+		match *self {
+			GeneratorKind::RecursiveSplit => "split",
+			GeneratorKind::Caves => "caves",
+			GeneratorKind::Rooms => "rooms",
+			GeneratorKind::DrunkardsWalk => "drunkard",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<GeneratorKind> {
+		GeneratorKind::all().iter().cloned().find(|kind| kind.name() == name)
+	}
+
+	/// The generator used for the level at the given depth when the game does not ask for a particular one.
+	/// The first level is always split into rooms; deeper levels take turns.
+	pub fn for_depth(depth: usize) -> GeneratorKind {
+		GeneratorKind::all()[(depth.max(1) - 1) % 4]
+	}
+
+	/// Generate terrain with this kind of generator, and make sure it is connected
+	pub fn generate<R: Rng>(&self, width: usize, height: usize, rng: &mut R) -> Grid<TerrainType> {
+		let mut terrain = match *self {
+			GeneratorKind::RecursiveSplit => {
+				let mut terrain = RecursiveSplit.generate(width, height, rng);
+				repair_doors(&mut terrain, START);
+				terrain
+			}
+			GeneratorKind::Caves => Caves.generate(width, height, rng),
+			GeneratorKind::Rooms => Rooms.generate(width, height, rng),
+			GeneratorKind::DrunkardsWalk => DrunkardsWalk.generate(width, height, rng),
+		};
+		terrain[START] = TerrainType::Floor;
+		connect(&mut terrain, START);
+		place_torches(&mut terrain, self.torches(), rng);
+		set_doors(&mut terrain, rng);
+		terrain
+	}
+
+	/// How many torches levels of this kind have. Natural caves and tunnels are darker than built rooms.
+	pub fn torches(&self) -> usize {
+		match *self {
+			GeneratorKind::RecursiveSplit => 12,
+			GeneratorKind::Caves => 4,
+			GeneratorKind::Rooms => 10,
+			GeneratorKind::DrunkardsWalk => 6,
+		}
+	}
+}
+
+/// Torches are kept at least this far apart, so their light spreads over the level
+const TORCH_SPACING: usize = 6;
+
+/// Hang up to `count` torches on walls facing open ground. A torch only goes on a wall with open ground on
+/// exactly one side, so that it does not light two rooms through the wall between them.
+pub fn place_torches<R: Rng>(terrain: &mut Grid<TerrainType>, count: usize, rng: &mut R) {
+	let sides = [(-1,0), (1,0), (0,-1), (0,1)];
+	let mut spots: Vec<Coord> = terrain.coords().filter(|&c| {
+		let open = sides.iter().filter(|&&(di,dj)| terrain.offset(c, di, dj).is_some_and(|n| terrain[n].is_passable())).count();
+		terrain[c] == TerrainType::Wall && open == 1
+	}).collect();
+	rng.shuffle(&mut spots);
+	let mut torches: Vec<Coord> = vec![];
+	for c in spots {
+		if torches.len() == count { break }
+		let near = |t: &Coord| t.0.abs_diff(c.0).max(t.1.abs_diff(c.1)) < TORCH_SPACING;
+		if torches.iter().any(near) { continue }
+		terrain[c] = TerrainType::Torch;
+		torches.push(c);
+	}
+}
+
+/// Percent chance that a door is left open, and that a door is locked
+const OPEN_DOORS: u32 = 30;
+const LOCKED_DOORS: u32 = 10;
+
+/// Leave some of the doors open and lock others. The rest stay closed. Only the player gets through locked
+/// doors, so the level stays connected for them.
+pub fn set_doors<R: Rng>(terrain: &mut Grid<TerrainType>, rng: &mut R) {
+	let doors: Vec<Coord> = terrain.coords().filter(|&c| terrain[c].is_door()).collect();
+	for c in doors {
+		let roll = rng.gen_range(0, 100);
+		if roll < OPEN_DOORS {
+			terrain[c] = TerrainType::OpenDoor;
+		} else if roll < OPEN_DOORS + LOCKED_DOORS {
+			terrain[c] = TerrainType::LockedDoor;
+		}
+	}
+}
+
+/// A grid of wall with the given tiles dug out
+fn walls(width: usize, height: usize) -> Grid<TerrainType> {
+	Grid::new(width, height, TerrainType::Wall)
+}
+
+/// Dijkstra map of the steps from `start` to every passable tile
+fn flood(terrain: &Grid<TerrainType>, start: Coord) -> DijkstraMap {
+	let mut tiles = terrain.map(|t| match t.is_passable() {
+		true => DijkstraTile::Passable,
+		false => DijkstraTile::Impassable,
+	});
+	if terrain[start].is_passable() { tiles[start] = DijkstraTile::Goal }
+	DijkstraMap::new(&tiles)
+}
+
+/// Whether every passable tile can be reached from `start`
+pub fn is_connected(terrain: &Grid<TerrainType>, start: Coord) -> bool {
+	let reached = flood(terrain, start);
+	terrain.coords().all(|c| !terrain[c].is_passable() || reached.value(c).is_some())
+}
+
+/// Join every passable tile that cannot be reached from `start` to the tiles that can. The nearest cut off
+/// tile is found with a dijkstra map spreading out from the reachable tiles through the walls, and a
+/// tunnel is dug back along it. This repeats until nothing is cut off.
+pub fn connect(terrain: &mut Grid<TerrainType>, start: Coord) {
+	loop {
+		let reached = flood(terrain, start);
+		let cut_off: Vec<Coord> = terrain.coords().filter(|&c| terrain[c].is_passable() && reached.value(c).is_none()).collect();
+		if cut_off.is_empty() { return }
+
+		// Anything but the outer edge may be dug through
+		let mut tiles = terrain.map(|_| DijkstraTile::Passable);
+		for c in terrain.coords() {
+			if terrain.on_edge(c) { tiles[c] = DijkstraTile::Impassable }
+			else if reached.value(c).is_some() { tiles[c] = DijkstraTile::Goal }
+		}
+		let tunnels = DijkstraMap::new(&tiles);
+		let mut c = match cut_off.iter().cloned().filter(|&c| tunnels.value(c).is_some()).min_by(|&a,&b| {
+			tunnels.value(a).partial_cmp(&tunnels.value(b)).unwrap()
+		}) {
+			Some(c) => c,
+			None => return,
+		};
+		while reached.value(c).is_none() {
+			if !terrain[c].is_passable() { terrain[c] = TerrainType::Floor }
+			let next = tunnels.get_next_step(c);
+			if next == c { break }
+			c = next;
+		}
+	}
+}
+
+/// What a level is made of, for checking generators over many seeds
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct Stats {
+	pub rooms: usize,			//areas of open ground, separated from each other by walls and doors
+	pub doors: usize,
+	pub dead_ends: usize,		//rooms with a single door
+	pub blocked_doors: usize,	//doors without open ground on two opposite sides
+	pub unreachable: usize,		//passable tiles that cannot be reached from the start
+}
+
+/// Count the rooms, doors and problems of a level
+pub fn stats(terrain: &Grid<TerrainType>, start: Coord) -> Stats {
+	let open = terrain.map(|t| t.is_passable() && !t.is_door());
+	let rooms = regions(&open);
+	let mut room_of = terrain.map(|_| None);
+	for (n, room) in rooms.iter().enumerate() {
+		for &c in room.iter() { room_of[c] = Some(n) }
+	}
+	// The rooms each door opens onto
+	let mut doors_of = vec![0; rooms.len()];
+	let mut output = Stats { rooms: rooms.len(), ..Stats::default() };
+	for c in terrain.coords().filter(|&c| terrain[c].is_door()) {
+		output.doors += 1;
+		if !is_doorway(terrain, c) { output.blocked_doors += 1 }
+		let mut next_to: Vec<usize> = terrain.neighbours(c).filter_map(|n| room_of[n]).collect();
+		next_to.sort();
+		next_to.dedup();
+		for n in next_to { doors_of[n] += 1 }
+	}
+	output.dead_ends = doors_of.iter().filter(|&&n| n == 1).count();
+	let reached = flood(terrain, start);
+	output.unreachable = terrain.coords().filter(|&c| terrain[c].is_passable() && reached.value(c).is_none()).count();
+	output
+}
+
+/// Whether the tile has passable tiles on two opposite sides, so that a door there leads somewhere
+fn is_doorway(terrain: &Grid<TerrainType>, c: Coord) -> bool {
+	let open = |di, dj| terrain.offset(c, di, dj).is_some_and(|n| terrain[n].is_passable() && !terrain[n].is_door());
+	(open(-1,0) && open(1,0)) || (open(0,-1) && open(0,1))
+}
+
+/// Check a level of rooms and repair it with doors. Doors that lead nowhere become wall again. Then, while
+/// some rooms cannot be reached from `start`, a door is put in a wall that has a reachable tile on one side
+/// and an unreachable one on the other. Returns the number of doors added.
+pub fn repair_doors(terrain: &mut Grid<TerrainType>, start: Coord) -> usize {
+	let blocked: Vec<Coord> = terrain.coords().filter(|&c| terrain[c].is_door() && !is_doorway(terrain, c)).collect();
+	for c in blocked { terrain[c] = TerrainType::Wall }
+
+	let mut added = 0;
+	loop {
+		let reached = flood(terrain, start);
+		let is_reached = |c: Option<Coord>| c.and_then(|c| reached.value(c)).is_some();
+		let is_cut_off = |c: Option<Coord>| c.is_some_and(|c| terrain[c].is_passable() && reached.value(c).is_none());
+		let door = terrain.coords().find(|&c| {
+			if terrain[c] != TerrainType::Wall || terrain.on_edge(c) { return false }
+			let (left, right) = (terrain.offset(c, -1, 0), terrain.offset(c, 1, 0));
+			let (up, down) = (terrain.offset(c, 0, -1), terrain.offset(c, 0, 1));
+			(is_reached(left) && is_cut_off(right)) || (is_cut_off(left) && is_reached(right)) ||
+				(is_reached(up) && is_cut_off(down)) || (is_cut_off(up) && is_reached(down))
+		});
+		match door {
+			Some(c) => {
+				terrain[c] = TerrainType::Door;
+				added += 1;
+			}
+			// Nothing is cut off, or only by walls too thick for a door. `connect` deals with the latter.
+			None => return added,
+		}
+	}
+}
+
+/// The groups of open tiles that touch each other, largest first
+fn regions(open: &Grid<bool>) -> Vec<Vec<Coord>> {
+	let mut seen = open.map(|_| false);
+	let mut output = vec![];
+	for c in open.coords() {
+		if seen[c] || !open[c] { continue }
+		let mut region = vec![];
+		let mut queue = VecDeque::new();
+		seen[c] = true;
+		queue.push_back(c);
+		while let Some(c) = queue.pop_front() {
+			region.push(c);
+			for n in open.neighbours(c) {
+				if !seen[n] && open[n] {
+					seen[n] = true;
+					queue.push_back(n);
+				}
+			}
+		}
+		output.push(region);
+	}
+	output.sort_by_key(|region| Reverse(region.len()));
+	output
+}
+
+/// The original generator. Splits the level in two with a wall, puts doors and a window in the wall, and
+/// does the same to each half until the rooms are small. Nothing here checks that the doors join every
+/// room up, so `GeneratorKind` follows it with `repair_doors`.
+pub struct RecursiveSplit;
+
+impl Generator for RecursiveSplit {
+	fn generate<R: Rng>(&self, width: usize, height: usize, rng: &mut R) -> Grid<TerrainType> {
+		//Initialize dungeon to walls with floor in middle
+		let mut dungeon = Grid::from_fn(width, height, |(m,n)| match (m,n) {
+			(m,n) if m==0 || n==0 || m==width-1 || n==height-1 => TerrainType::Wall,
+			_ => TerrainType::Floor,
+		});
+		/// Define recursive splitting function
+		/// This function takes a rectangle and splits it into two smaller rectangles
+		/// by drawing a line through it. It places up to 2 doors and 1 window on that line.
+		/// The recursion terminates once the rectangles reach size 3.
+		/// Lines are only drawn on even numbered rows/columns
+		fn rec_split<R: Rng>(d: &mut Grid<TerrainType>, rng: &mut R, x0:usize, x1:usize, y0:usize, y1:usize) {
+			let (width,height) = (x1-x0+1,y1-y0+1);
+			let mut terrains = vec![TerrainType::Window,TerrainType::Door,TerrainType::Door];
+			let mut locations = vec![];
+			let mut splitpoints = vec![];
+			// First, exit if the rectangle is too small
+			if width <= 5 || height <= 5 { return; }
+
+			// Split rectangle across longer axis
+			match width < height {
+				true => { // draw horizontal line. First, select a random row
+					let mut row = y0+4;
+					//Select a point.
+					for y in y0+2..y1-2 { if y%2==0 { splitpoints.push(y) } };
+					rng.shuffle(&mut splitpoints);
+					loop {
+						match splitpoints.pop() {
+							Some(y) => if let (TerrainType::Wall,TerrainType::Wall) = (&d[(x0,y)],&d[(x1,y)]) {
+								for x in x0..x1 { 
+									row=y; 
+									d[(x,y)] = TerrainType::Wall;
+								};
+								break; 
+							},
+							// No wall can be drawn across, so the rectangle stays one room
+							_ => return,
+						}
+					}
+					//Then we randomly place up to 2 doors and 1 window on the new wall
+					//First, get a vector of even locations that are free
+					
+					
+					for x in x0+2..x1-2 { if x%2==0 { locations.push(x) } };
+					//Then shuffle it. Pop the first three elements (up to) and turn them into doors and a window
+					rng.shuffle(&mut locations);
+					for _ in 0..3 {
+						let terrain_type = match terrains.pop() {
+							Some(t) => t,
+							None => unreachable!(),
+						};
+						match locations.pop() {
+							Some(x) => d[(x,row)] = terrain_type,
+							None => break,
+						};
+					}
+					// Now call this function on each of the two smaller rooms
+					
+					rec_split(d, rng, x0, x1, y0, row);
+					rec_split(d, rng, x0, x1, row, y1);
+				}
+				false => { // draw vertical line. First, select a random column
+					let mut column = x0 + 4;
+					//Select a point.
+					for x in x0+2..x1-2 { if x%2==0 { splitpoints.push(x) } };
+					rng.shuffle(&mut splitpoints);
+					loop {
+						match splitpoints.pop() {
+							Some(x) => if let (TerrainType::Wall,TerrainType::Wall) = (&d[(x,y0)],&d[(x,y1)]) {
+								for y in y0..y1 { 
+									column = x;  
+									d[(x,y)] = TerrainType::Wall;
+								};
+								break;
+							},
+							// No wall can be drawn across, so the rectangle stays one room
+							_ => return,
+						}
+					}
+					//Then we randomly place up to 2 doors and 1 window on the new wall
+					//First, get a vector of even locations that are free
+					for y in y0+2..y1-2 { if y%2==0 { locations.push(y) } };
+					//Then shuffle it. Pop the first three elements (up to) and turn them into doors and a window
+					rng.shuffle(&mut locations);
+					for _ in 0..3 {
+						let terrain_type = match terrains.pop() {
+							Some(t) => t,
+							None => unreachable!(),
+						};
+						match locations.pop() {
+							Some(y) => d[(column,y)] = terrain_type,
+							None => break,
+						};
+					}
+					// Now call this function on each of the two smaller rooms
+					rec_split(d, rng, x0, column, y0, y1);
+					rec_split(d, rng, column, x1, y0, y1);
+				}
+			}
+		}
+		rec_split(&mut dungeon,rng,0,width-1,0,height-1);
+		dungeon
+	}
+}
+
+/// Caves, made by filling the level with random walls and letting each tile become wall or floor depending
+/// on how many walls surround it. Small pockets are filled in; larger ones are joined to the rest.
+pub struct Caves;
+
+/// Fraction of the level that starts out as wall
+const CAVE_FILL: f64 = 0.45;
+/// Number of times the automaton is run
+const CAVE_STEPS: usize = 5;
+/// Pockets smaller than this are filled in rather than joined to the rest
+const CAVE_MIN_POCKET: usize = 12;
+
+impl Generator for Caves {
+	fn generate<R: Rng>(&self, width: usize, height: usize, rng: &mut R) -> Grid<TerrainType> {
+		let edge = |(i,j): Coord| i == 0 || j == 0 || i == width-1 || j == height-1;
+		let mut cave = Grid::from_fn(width, height, |c| match edge(c) || rng.next_f64() < CAVE_FILL {
+			true => TerrainType::Wall,
+			false => TerrainType::Floor,
+		});
+		for _ in 0..CAVE_STEPS {
+			cave = Grid::from_fn(width, height, |c| {
+				// Tiles off the edge of the level count as wall
+				let walls = 8 - cave.neighbours(c).count() + cave.neighbours(c).filter(|&n| !cave[n].is_passable()).count();
+				match edge(c) || walls >= 5 || (walls == 4 && !cave[c].is_passable()) {
+					true => TerrainType::Wall,
+					false => TerrainType::Floor,
+				}
+			});
+		}
+		for region in regions(&cave.map(|t| t.is_passable())).iter().skip(1) {
+			if region.len() < CAVE_MIN_POCKET {
+				for &c in region.iter() { cave[c] = TerrainType::Wall }
+			}
+		}
+		cave
+	}
+}
+
+/// Rectangular rooms scattered over the level, each joined to the one before it by a corridor
+pub struct Rooms;
+
+/// Number of rooms the generator tries to place
+const ROOM_ATTEMPTS: usize = 60;
+/// Smallest and largest room sides
+const ROOM_MIN: usize = 3;
+const ROOM_MAX: usize = 9;
+
+impl Generator for Rooms {
+	fn generate<R: Rng>(&self, width: usize, height: usize, rng: &mut R) -> Grid<TerrainType> {
+		let mut level = walls(width, height);
+		// Rooms as (left, top, width, height). The first is in the corner, around the start.
+		let mut rooms = vec![(START.0, START.1, ROOM_MIN + 1, ROOM_MIN + 1)];
+		for _ in 0..ROOM_ATTEMPTS {
+			let (w, h) = (rng.gen_range(ROOM_MIN, ROOM_MAX+1), rng.gen_range(ROOM_MIN, ROOM_MAX+1));
+			if w + 2 >= width || h + 2 >= height { continue }
+			let (x, y) = (rng.gen_range(1, width - w), rng.gen_range(1, height - h));
+			// Keep a wall between rooms
+			let overlaps = rooms.iter().any(|&(x2,y2,w2,h2)| x <= x2 + w2 && x2 <= x + w && y <= y2 + h2 && y2 <= y + h);
+			if !overlaps { rooms.push((x, y, w, h)) }
+		}
+		for &(x,y,w,h) in rooms.iter() {
+			for j in y..y+h { for i in x..x+w { level[(i,j)] = TerrainType::Floor } }
+		}
+		// Corridors run from the middle of each room to the middle of the one before, turning once
+		for pair in rooms.windows(2) {
+			let (a, b) = (pair[0], pair[1]);
+			let from = (a.0 + a.2/2, a.1 + a.3/2);
+			let to = (b.0 + b.2/2, b.1 + b.3/2);
+			let corner = match rng.gen() {
+				true => (to.0, from.1),
+				false => (from.0, to.1),
+			};
+			dig_line(&mut level, from, corner);
+			dig_line(&mut level, corner, to);
+		}
+		level
+	}
+}
+
+/// Turn the tiles on a horizontal or vertical line into floor
+fn dig_line(level: &mut Grid<TerrainType>, from: Coord, to: Coord) {
+	for i in from.0.min(to.0)..from.0.max(to.0)+1 {
+		for j in from.1.min(to.1)..from.1.max(to.1)+1 {
+			if !level[(i,j)].is_passable() { level[(i,j)] = TerrainType::Floor }
+		}
+	}
+}
+
+/// Tunnels dug by a walker that stumbles around at random from the start until enough of the level is floor.
+/// Everything it digs is connected by construction.
+pub struct DrunkardsWalk;
+
+/// Fraction of the inside of the level the walker digs out
+const DRUNKARD_FLOOR: f64 = 0.4;
+
+impl Generator for DrunkardsWalk {
+	fn generate<R: Rng>(&self, width: usize, height: usize, rng: &mut R) -> Grid<TerrainType> {
+		let mut level = walls(width, height);
+		let inside = (width.saturating_sub(2) * height.saturating_sub(2)) as f64;
+		let mut dug = 0;
+		let mut c = START;
+		while (dug as f64) < inside * DRUNKARD_FLOOR {
+			if !level[c].is_passable() {
+				level[c] = TerrainType::Floor;
+				dug += 1;
+			}
+			let (di, dj) = *rng.choose(&[(-1,0),(1,0),(0,-1),(0,1)]).unwrap();
+			if let Some(next) = level.offset(c, di, dj) {
+				if !level.on_edge(next) { c = next }
+			}
+		}
+		level
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rng::GameRng;
+
+	const SEEDS: u64 = 200;
+	const STATS_SEEDS: u64 = 1000;
+
+	/// What a generator of the given kind makes before `connect` has joined its pockets
+	fn raw(kind: GeneratorKind, width: usize, height: usize, rng: &mut GameRng) -> Grid<TerrainType> {
+		match kind {
+			GeneratorKind::RecursiveSplit => RecursiveSplit.generate(width, height, rng),
+			GeneratorKind::Caves => Caves.generate(width, height, rng),
+			GeneratorKind::Rooms => Rooms.generate(width, height, rng),
+			GeneratorKind::DrunkardsWalk => DrunkardsWalk.generate(width, height, rng),
+		}
+	}
+
+	#[test]
+	fn connect_joins_every_pocket() {
+		for kind in GeneratorKind::all().iter() {
+			for seed in 0..SEEDS {
+				let (width, height) = (12 + seed as usize % 40, 12 + (seed as usize / 40) % 40);
+				let mut terrain = raw(*kind, width, height, &mut GameRng::new(seed));
+				terrain[START] = TerrainType::Floor;
+				connect(&mut terrain, START);
+				assert!(is_connected(&terrain, START), "{} seed {} ({}x{}): still cut off", kind.name(), seed, width, height);
+			}
+		}
+	}
+
+	#[test]
+	fn levels_are_connected() {
+		for kind in GeneratorKind::all().iter() {
+			for seed in 0..SEEDS {
+				let terrain = kind.generate(42, 42, &mut GameRng::new(seed));
+				assert!(terrain[START].is_passable(), "{} seed {}: the start is not passable", kind.name(), seed);
+				assert!(is_connected(&terrain, START), "{} seed {}: some tiles cannot be reached", kind.name(), seed);
+			}
+		}
+	}
+
+	/// Checks the stats of many levels of one kind: every passable tile can be reached, and a splitter's doors
+	/// all lead somewhere and are enough to join its rooms
+	fn check_stats(kind: GeneratorKind) {
+		for seed in 0..STATS_SEEDS {
+			let terrain = kind.generate(42, 42, &mut GameRng::new(seed));
+			let s = stats(&terrain, START);
+			assert_eq!(s.unreachable, 0, "{} seed {}: {} tiles cannot be reached", kind.name(), seed, s.unreachable);
+			if kind == GeneratorKind::RecursiveSplit {
+				assert_eq!(s.blocked_doors, 0, "split seed {}: a door leads nowhere", seed);
+				assert!(s.doors + 1 >= s.rooms, "split seed {}: {} doors cannot join {} rooms", seed, s.doors, s.rooms);
+			}
+		}
+	}
+
+	#[test]
+	fn recursive_split_stats() { check_stats(GeneratorKind::RecursiveSplit) }
+
+	#[test]
+	fn caves_stats() { check_stats(GeneratorKind::Caves) }
+
+	#[test]
+	fn rooms_stats() { check_stats(GeneratorKind::Rooms) }
+
+	#[test]
+	fn drunkards_walk_stats() { check_stats(GeneratorKind::DrunkardsWalk) }
+
+	#[test]
+	fn repair_doors_fixes_every_split_size() {
+		for seed in 0..STATS_SEEDS {
+			let (width, height) = (12 + seed as usize % 60, 12 + (seed as usize / 60) % 60);
+			let mut terrain = RecursiveSplit.generate(width, height, &mut GameRng::new(seed));
+			repair_doors(&mut terrain, START);
+			let s = stats(&terrain, START);
+			assert_eq!(s.unreachable, 0, "split seed {} ({}x{}): rooms are still cut off", seed, width, height);
+			assert_eq!(s.blocked_doors, 0, "split seed {} ({}x{}): a door still leads nowhere", seed, width, height);
+		}
+	}
+
+	#[test]
+	fn repair_doors_opens_a_walled_off_room() {
+		// Two rooms with a wall between them and no door
+		let rows = [
+			"###########",
+			"#....#....#",
+			"#....#....#",
+			"#....#....#",
+			"###########",
+		];
+		let mut terrain = Grid::from_rows(rows.iter().map(|row| row.chars().map(|ch| match ch {
+			'#' => TerrainType::Wall,
+			_ => TerrainType::Floor,
+		}).collect()).collect());
+		assert!(!is_connected(&terrain, START));
+		assert_eq!(repair_doors(&mut terrain, START), 1);
+		assert!(is_connected(&terrain, START));
+	}
+}
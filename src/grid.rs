@@ -0,0 +1,137 @@
+//! A rectangular grid of values stored in a single vector. Both the dungeon map and dijkstra maps are built
+//! on it, so there is one indexing convention everywhere: a coordinate is `(i,j)`, where `i` is the column
+//! (x) and `j` is the row (y).
+
+use std::ops::{Index,IndexMut};
+use std::slice;
+
+/// The coordinates of a cell, as (column, row)
+pub type Coord = (usize,usize);
+
+#[derive(Clone)]
+pub struct Grid<T> {
+	width: usize,
+	height: usize,
+	cells: Vec<T>,	// row after row
+}
+
+impl<T: Clone> Grid<T> {
+	/// Create a grid with every cell set to the given value
+	pub fn new(width: usize, height: usize, value: T) -> Grid<T> {
+		Grid { width: width, height: height, cells: vec![value; width*height] }
+	}
+}
+
+impl<T> Grid<T> {
+	/// Create a grid by calling a function on the coordinates of each cell
+	pub fn from_fn<F: FnMut(Coord) -> T>(width: usize, height: usize, mut f: F) -> Grid<T> {
+		let mut cells = Vec::with_capacity(width*height);
+		for j in 0..height { for i in 0..width { cells.push(f((i,j))) } }
+		Grid { width: width, height: height, cells: cells }
+	}
+
+	/// Create a grid from a vector of rows. All rows must have the same length.
+	pub fn from_rows(rows: Vec<Vec<T>>) -> Grid<T> {
+		let height = rows.len();
+		let width = rows.first().map_or(0, |row| row.len());
+		assert!(rows.iter().all(|row| row.len() == width), "grid rows must all have the same length");
+		Grid { width: width, height: height, cells: rows.into_iter().flat_map(|row| row.into_iter()).collect() }
+	}
+
+	pub fn width(&self) -> usize { self.width }
+	pub fn height(&self) -> usize { self.height }
+	pub fn len(&self) -> usize { self.cells.len() }
+	pub fn is_empty(&self) -> bool { self.cells.is_empty() }
+
+	pub fn contains(&self, c: Coord) -> bool { c.0 < self.width && c.1 < self.height }
+
+	/// Whether c is on the outer edge of the grid
+	pub fn on_edge(&self, c: Coord) -> bool {
+		c.0 == 0 || c.1 == 0 || c.0 == self.width-1 || c.1 == self.height-1
+	}
+
+	/// Bounds-checked access
+	pub fn get(&self, c: Coord) -> Option<&T> {
+		if self.contains(c) { Some(&self.cells[self.index(c)]) } else { None }
+	}
+	pub fn get_mut(&mut self, c: Coord) -> Option<&mut T> {
+		if self.contains(c) { let n = self.index(c); Some(&mut self.cells[n]) } else { None }
+	}
+
+	/// Conversion between coordinates and positions in the underlying vector
+	pub fn index(&self, c: Coord) -> usize { c.1*self.width + c.0 }
+	pub fn coord(&self, index: usize) -> Coord { (index % self.width, index / self.width) }
+
+	/// The coordinates reached by stepping (di,dj) from c, if they are inside the grid
+	pub fn offset(&self, c: Coord, di: isize, dj: isize) -> Option<Coord> {
+		let (i,j) = (c.0 as isize + di, c.1 as isize + dj);
+		if i < 0 || j < 0 || i >= self.width as isize || j >= self.height as isize { return None }
+		Some((i as usize, j as usize))
+	}
+
+	/// The (up to 8) cells around c that are inside the grid
+	pub fn neighbours(&self, c: Coord) -> Neighbours {
+		Neighbours { center: c, width: self.width, height: self.height, n: 0 }
+	}
+
+	/// Iterate over the cells, row after row
+	pub fn iter(&self) -> slice::Iter<'_,T> { self.cells.iter() }
+	pub fn iter_mut(&mut self) -> slice::IterMut<'_,T> { self.cells.iter_mut() }
+	pub fn rows(&self) -> slice::Chunks<'_,T> { self.cells.chunks(self.width) }
+
+	/// Iterate over the coordinates of every cell, row after row
+	pub fn coords(&self) -> Coords { Coords { width: self.width, end: self.cells.len(), n: 0 } }
+
+	/// Create a grid of the same size by applying a function to every cell
+	pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Grid<U> {
+		Grid { width: self.width, height: self.height, cells: self.cells.iter().map(f).collect() }
+	}
+}
+
+impl<T> Index<Coord> for Grid<T> {
+	type Output = T;
+	fn index(&self, c: Coord) -> &T {
+		assert!(self.contains(c), "coordinates {:?} are outside the {}x{} grid", c, self.width, self.height);
+		&self.cells[c.1*self.width + c.0]
+	}
+}
+
+impl<T> IndexMut<Coord> for Grid<T> {
+	fn index_mut(&mut self, c: Coord) -> &mut T {
+		assert!(self.contains(c), "coordinates {:?} are outside the {}x{} grid", c, self.width, self.height);
+		&mut self.cells[c.1*self.width + c.0]
+	}
+}
+
+/// Iterator over the coordinates of a grid
+pub struct Coords { width: usize, end: usize, n: usize }
+
+impl Iterator for Coords {
+	type Item = Coord;
+	fn next(&mut self) -> Option<Coord> {
+		if self.n >= self.end { return None }
+		let c = (self.n % self.width, self.n / self.width);
+		self.n += 1;
+		Some(c)
+	}
+}
+
+// Offsets to the neighbours of a cell. Orthogonal steps come first, so that callers who stop at the first of
+// several equally good neighbours prefer moving in straight lines.
+const NEIGHBOURS: [(isize,isize);8] = [(-1,0),(0,1),(1,0),(0,-1),(-1,1),(1,1),(1,-1),(-1,-1)];
+
+/// Iterator over the neighbours of a cell
+pub struct Neighbours { center: Coord, width: usize, height: usize, n: usize }
+
+impl Iterator for Neighbours {
+	type Item = Coord;
+	fn next(&mut self) -> Option<Coord> {
+		while self.n < NEIGHBOURS.len() {
+			let (di,dj) = NEIGHBOURS[self.n];
+			self.n += 1;
+			let (i,j) = (self.center.0 as isize + di, self.center.1 as isize + dj);
+			if i >= 0 && j >= 0 && i < self.width as isize && j < self.height as isize { return Some((i as usize, j as usize)) }
+		}
+		None
+	}
+}
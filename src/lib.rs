@@ -0,0 +1,30 @@
+/*! # Spellbound
+
+A roguelike game which uses dijkstra maps for ai and pathfinding.
+
+This library is the headless game core: map generation, vision, dijkstra maps and turns. It has no
+dependency on any window or graphics context, so it can drive tests, bots and servers as well as the
+piston front-end in `main.rs`.
+
+*/
+// Struct literals name every field, as in `Grid { width: width, .. }`
+#![allow(clippy::redundant_field_names)]
+
+extern crate rand;
+
+pub mod grid;
+pub mod game;
+pub mod object;
+pub mod dungeon;
+pub mod fov;
+pub mod generator;
+pub mod dijkstra_map;
+pub mod map_cache;
+pub mod rng;
+pub mod save;
+pub mod replay;
+pub mod data;
+pub mod spell;
+pub mod monster;
+pub mod ai;
+pub mod controls;
@@ -1,45 +1,285 @@
-/*! # Spellbound
+//! Piston front-end for Spellbound. Translates window input into game commands and draws the game state.
+// Struct literals name every field, as in the library
+#![allow(clippy::redundant_field_names)]
 
-A roguelike game which uses dijkstra maps for ai and pathfinding.
-
-*/
 extern crate piston_window;
 extern crate gfx_device_gl;
 extern crate find_folder;
 extern crate gfx_graphics;
 extern crate gfx;
 extern crate rand;
+extern crate spellbound;
 
-mod game;
-mod object;
-mod dungeon;
 mod sprite;
-mod dijkstra_map;
+mod render;
+mod terminal;
 
-fn main() {
-    use piston_window::*;
-    use game::Game;
+use piston_window::*;
+use spellbound::game::Game;
+use spellbound::generator::GeneratorKind;
+use spellbound::controls::{Controls,Key as Control};
+use spellbound::replay::Replay;
+use spellbound::data::DataError;
+use spellbound::monster::{MonsterDef,parse_monsters,builtin_monsters};
+use spellbound::spell::{SpellDef,parse_spells,builtin_spells};
+
+/// Translate a window input into a key for the controls
+fn control(inp: &Input) -> Option<Control> {
+    match inp {
+        &Input::Press(key) => {
+            match key {
+                //Arrow keys
+                Button::Keyboard(Key::Up) => Some(Control::Direction(0,-1)),
+                Button::Keyboard(Key::Down) => Some(Control::Direction(0,1)),
+                Button::Keyboard(Key::Left) => Some(Control::Direction(-1,0)),
+                Button::Keyboard(Key::Right) => Some(Control::Direction(1,0)),
+                //Numpad and number keys. They move as on the numpad, or pick from menus.
+                Button::Keyboard(Key::NumPad1) | Button::Keyboard(Key::D1) => Some(Control::Number(1)),
+                Button::Keyboard(Key::NumPad2) | Button::Keyboard(Key::D2) => Some(Control::Number(2)),
+                Button::Keyboard(Key::NumPad3) | Button::Keyboard(Key::D3) => Some(Control::Number(3)),
+                Button::Keyboard(Key::NumPad4) | Button::Keyboard(Key::D4) => Some(Control::Number(4)),
+                Button::Keyboard(Key::NumPad5) | Button::Keyboard(Key::D5) => Some(Control::Number(5)),
+                Button::Keyboard(Key::NumPad6) | Button::Keyboard(Key::D6) => Some(Control::Number(6)),
+                Button::Keyboard(Key::NumPad7) | Button::Keyboard(Key::D7) => Some(Control::Number(7)),
+                Button::Keyboard(Key::NumPad8) | Button::Keyboard(Key::D8) => Some(Control::Number(8)),
+                Button::Keyboard(Key::NumPad9) | Button::Keyboard(Key::D9) => Some(Control::Number(9)),
+                //Automated movement
+                Button::Keyboard(Key::O) => Some(Control::Automove),
+                //Stairs, on the keys that hold '>' and '<'
+                Button::Keyboard(Key::Period) => Some(Control::Descend),
+                Button::Keyboard(Key::Comma) => Some(Control::Ascend),
+                //Spells. Escape closes the window, so backspace cancels aiming.
+                Button::Keyboard(Key::Z) => Some(Control::Cast),
+                Button::Keyboard(Key::Tab) => Some(Control::NextTarget),
+                Button::Keyboard(Key::Return) => Some(Control::Confirm),
+                Button::Keyboard(Key::Backspace) => Some(Control::Cancel),
+                //Doors
+                Button::Keyboard(Key::E) => Some(Control::Open),
+                Button::Keyboard(Key::C) => Some(Control::Close),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Settings read from the command line
+struct Options {
+    seed: Option<u64>,      // --seed <n>: start a new game with this seed
+    load: Option<String>,   // --load <file>: continue a saved game
+    save: String,           // --save <file>: where to save the game on quit
+    record: String,         // --record <file>: where to write the replay of a new game on quit
+    replay: Option<String>, // --replay <file>: watch a replay instead of playing
+    headless: bool,         // --headless: run the replay without opening a window
+    speed: f64,             // --speed <n>: turns per second when watching a replay
+    terminal: bool,         // --terminal: play in the terminal instead of a window
+    generator: Option<GeneratorKind>, // --generator <name>: make every level of a new game with this generator
+}
+
+/// Get the value following a flag on the command line, if the flag is present
+fn flag(args: &[String], name: &str) -> Option<String> {
+    for n in 0..args.len() {
+        if args[n] == name {
+            match args.get(n+1) {
+                Some(value) => return Some(value.clone()),
+                None => {
+                    println!("{} expects a value", name);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Get the number following a flag on the command line, if the flag is present
+fn number<T: std::str::FromStr>(args: &[String], name: &str) -> Option<T> {
+    flag(args,name).map(|s| match s.parse() {
+        Ok(n) => n,
+        Err(_) => {
+            println!("{} expects a number", name);
+            std::process::exit(1);
+        }
+    })
+}
+
+fn options() -> Options {
+    let args: Vec<String> = std::env::args().collect();
+    Options {
+        seed: number(&args,"--seed"),
+        load: flag(&args,"--load"),
+        save: flag(&args,"--save").unwrap_or("spellbound.sav".to_string()),
+        record: flag(&args,"--record").unwrap_or("spellbound.replay".to_string()),
+        replay: flag(&args,"--replay"),
+        headless: args.iter().any(|arg| arg == "--headless"),
+        speed: number(&args,"--speed").unwrap_or(10.0),
+        terminal: args.iter().any(|arg| arg == "--terminal"),
+        generator: flag(&args,"--generator").map(|name| match GeneratorKind::from_name(&name) {
+            Some(kind) => kind,
+            None => {
+                let names: Vec<&str> = GeneratorKind::all().iter().map(|kind| kind.name()).collect();
+                println!("--generator expects one of {}", names.join(", "));
+                std::process::exit(1);
+            }
+        }),
+    }
+}
+
+/// Read a data file from the assets folder, so it can be changed without recompiling. Falls back on the
+/// built-in copy if there is no assets folder, and stops the game if the file has a mistake in it.
+fn load_data<T>(name: &str, builtin: fn() -> Vec<T>, parse: fn(&str, &str) -> Result<Vec<T>,DataError>) -> Vec<T> {
+    use std::io::Read;
+    let path = match find_folder::Search::ParentsThenKids(3, 3).for_folder("assets") {
+        Ok(assets) => assets.join(name),
+        Err(_) => return builtin(),
+    };
+    let mut text = String::new();
+    if let Err(e) = std::fs::File::open(&path).and_then(|mut file| file.read_to_string(&mut text)) {
+        println!("Could not read {}: {}", path.display(), e);
+        std::process::exit(1);
+    }
+    match parse(name, &text) {
+        Ok(defs) => defs,
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Play the game in a piston window until it is closed. Replays are played at the given speed in turns per
+/// second, and commands the player issues are added to the recording.
+fn run_window(game: &mut Game, recording: &mut Option<Replay>, replay: &Option<Replay>, speed: f64) {
+    use render::Renderer;
+    // The seed is shown in the title bar so it can be copied into bug reports
     let window: PistonWindow = WindowSettings::new(
-        "Spellbound",
+        format!("Spellbound (seed {})", game.seed()),
         [600, 600]
     )
     .exit_on_esc(true)
     .build()
     .unwrap();
-    let mut game = Game::new(&window);
-    game.on_load();
+    let mut renderer = Renderer::new();
+    let mut replayed = 0;   // number of replay commands played so far
+    let mut timer = 0.0;    // time since the last replay command
+    let mut controls = Controls::new();
+    let mut shown = 0;      // number of game messages printed so far
 
     for e in window {
         match e.event {
             Some(Event::Render(ren)) => {
-                game.on_draw(ren, e);
+                renderer.draw(game, &controls, ren, e);
+            }
+            Some(Event::Update(args)) => {
+                if let Some(ref replay) = *replay {
+                    timer += args.dt;
+                    while timer >= 1.0 / speed && replayed < replay.commands.len() {
+                        timer -= 1.0 / speed;
+                        game.step(replay.commands[replayed]);
+                        replayed += 1;
+                    }
+                }
             }
-            Some(Event::Input(ref inp)) => {
-                game.on_input(inp, &e);
+            Some(Event::Input(ref inp)) if replay.is_none() => {
+                if let Some(key) = control(inp) {
+                    let command = controls.press(game, key);
+                    if game.step(command) {
+                        if let Some(ref mut recording) = *recording { recording.record(command) }
+                    }
+                }
             }
             _ => {
 
             }
         }
+        // The window has no room for text, so messages go to the console
+        for message in game.messages()[shown..].iter() { println!("{}", message) }
+        if game.is_over() && shown < game.messages().len() {
+            println!("GAME OVER after {} turns on level {}. Press escape to quit.", game.turn(), game.depth());
+        }
+        shown = game.messages().len();
+    }
+}
+
+fn main() {
+    use std::path::Path;
+    use spellbound::save;
+    let options = options();
+    let spells: Vec<SpellDef> = load_data("spells.txt", builtin_spells, parse_spells);
+    let monsters: Vec<MonsterDef> = load_data("monsters.txt", builtin_monsters, parse_monsters);
+
+    // When watching a replay, the commands come from the file rather than the keyboard
+    let replay = match options.replay {
+        Some(ref file) => match Replay::load(Path::new(file)) {
+            Ok(replay) => Some(replay),
+            Err(e) => {
+                println!("Could not load {}: {}", file, e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    if options.headless {
+        match replay {
+            Some(ref replay) => {
+                let game = match replay.play(spells, monsters) {
+                    Ok(game) => game,
+                    Err(e) => {
+                        println!("Could not play the replay: {}", e);
+                        std::process::exit(1);
+                    }
+                };
+                println!("Replay of seed {} finished after {} turns with the player at {:?}", game.seed(), game.turn(), game.player().coordinates());
+                if game.is_over() { println!("The player died.") }
+            }
+            None => println!("--headless can only be used with --replay"),
+        }
+        return;
+    }
+
+    let mut game = match (&replay, &options.load) {
+        (&Some(ref replay), _) => match replay.start(spells.clone(), monsters.clone()) {
+            Ok(game) => game,
+            Err(e) => {
+                println!("Could not play the replay: {}", e);
+                std::process::exit(1);
+            }
+        },
+        (_, &Some(ref file)) => match save::load(Path::new(file)) {
+            Ok(game) => game,
+            Err(e) => {
+                println!("Could not load {}: {}", file, e);
+                std::process::exit(1);
+            }
+        },
+        _ => Game::with_generator(options.seed.unwrap_or_else(rand::random), options.generator),
+    };
+    game.set_spells(spells);
+    game.set_monsters(monsters);
+    // Only new games can be recorded, since a replay starts from the seed
+    let mut recording = match (&replay, &options.load) {
+        (&None, &None) => Some(Replay::new(game.seed(), game.generator(), game.spells(), game.monsters())),
+        _ => None,
+    };
+    println!("Playing game with seed {}", game.seed());
+    if options.terminal {
+        terminal::run(&mut game, &mut recording, &replay, options.speed);
+    } else {
+        run_window(&mut game, &mut recording, &replay, options.speed);
+    }
+
+    // The player has quit, so save the game. Watching a replay leaves the save alone, and a dead player's
+    // run is over for good.
+    if replay.is_none() && !game.is_over() {
+        match save::save(&game, Path::new(&options.save)) {
+            Ok(()) => println!("Game saved to {}", options.save),
+            Err(e) => println!("Could not save game: {}", e),
+        }
+    }
+    if let Some(recording) = recording {
+        match recording.save(Path::new(&options.record)) {
+            Ok(()) => println!("Replay saved to {}", options.record),
+            Err(e) => println!("Could not save replay: {}", e),
+        }
     }
 }
@@ -0,0 +1,84 @@
+//! A cache of named dijkstra maps. Building a map takes time proportional to the size of the level, so maps
+//! that many creatures use (the player's position, unseen tiles and so on) are built once and shared.
+//!
+//! Every map is stored with the goals it was built from. Asking for a map with the same goals returns the
+//! stored copy; asking with different goals rebuilds it. Goals that move, like the player, change nearly
+//! every value of the map, and a rebuild handles that faster than updating the map in place. Changes to the
+//! terrain affect every map, so they must be reported: a single tile that changes, such as a door opening,
+//! with `set_cost`, and anything more with `invalidate`. Maps nobody asked for during a turn are dropped at
+//! the end of it, so one-off maps do not pile up.
+
+use std::collections::HashMap;
+use dijkstra_map::DijkstraMap;
+use dungeon::Map;
+use grid::Coord;
+
+struct Entry {
+    goals: Vec<Coord>,  //what the map was built from
+    map: DijkstraMap,
+    used: bool,         //whether the map was asked for this turn
+    of_level: bool,     //whether the map was built straight from the level, so that it can be updated in place
+}
+
+pub struct MapCache {
+    entries: HashMap<String,Entry>,
+    builds: u64,        //number of maps built so far, for measuring how well the cache works
+}
+
+impl Default for MapCache {
+    fn default() -> MapCache { MapCache::new() }
+}
+
+impl MapCache {
+    pub fn new() -> MapCache { MapCache { entries: HashMap::new(), builds: 0 } }
+
+    /// The map with the given name. If it is missing, or was built from different goals, it is built by
+    /// calling `build`.
+    pub fn get_or_build<F: FnOnce() -> DijkstraMap>(&mut self, name: &str, goals: Vec<Coord>, build: F) -> &DijkstraMap {
+        self.fetch(name, goals, false, build)
+    }
+
+    /// The map of the given level with the given name, leading to the given goals
+    pub fn get(&mut self, map: &Map, name: &str, goals: Vec<Coord>) -> &DijkstraMap {
+        let copy = goals.clone();
+        self.fetch(name, goals, true, || map.get_dijkstra_map(copy))
+    }
+
+    fn fetch<F: FnOnce() -> DijkstraMap>(&mut self, name: &str, goals: Vec<Coord>, of_level: bool, build: F) -> &DijkstraMap {
+        let fresh = match self.entries.get(name) {
+            Some(entry) => entry.goals == goals && entry.of_level == of_level,
+            None => false,
+        };
+        if !fresh {
+            self.builds += 1;
+            self.entries.insert(name.to_string(), Entry { goals: goals, map: build(), used: false, of_level: of_level });
+        }
+        let entry = self.entries.get_mut(name).unwrap();
+        entry.used = true;
+        &entry.map
+    }
+
+    /// Report that the cost of stepping onto the tile at c has changed, with None if it became impassable.
+    /// Maps built from the level are updated in place. Those built some other way, and those with a goal on
+    /// the tile (goals only count on passable ground), are forgotten instead.
+    pub fn set_cost(&mut self, c: Coord, cost: Option<f64>) {
+        self.entries.retain(|_, entry| entry.of_level && !entry.goals.contains(&c));
+        for entry in self.entries.values_mut() { entry.map.set_cost(c, cost) }
+    }
+
+    /// Forget every map, for when the terrain has changed
+    pub fn invalidate(&mut self) { self.entries.clear() }
+
+    /// Forget one map, for when something it depends on other than its goals has changed
+    pub fn remove(&mut self, name: &str) { self.entries.remove(name); }
+
+    /// Drop the maps that were not used since the last call
+    pub fn end_turn(&mut self) {
+        self.entries.retain(|_, entry| entry.used);
+        for entry in self.entries.values_mut() { entry.used = false }
+    }
+
+    pub fn len(&self) -> usize { self.entries.len() }
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+    pub fn builds(&self) -> u64 { self.builds }
+}
@@ -0,0 +1,184 @@
+//! Monster types. Like spells, monsters are data: front-ends read `assets/monsters.txt` when the game starts,
+//! so new creatures can be added without recompiling. A copy of the file is built in for headless runs.
+
+use ai::DESIRES;
+use data;
+use data::DataError;
+use grid::Coord;
+use object::{Behavior,Creature,DEFAULT_SIGHT};
+
+#[derive(Clone,Debug)]
+pub struct MonsterDef {
+    pub name: String,
+    pub sprite: String,
+    pub glyph: char,
+    pub speed: i32,
+    pub hp: i32,
+    pub attack: i32,
+    pub defense: i32,
+    pub behavior: Behavior,
+    pub desires: Vec<(String,f64)>,
+    pub flee: Vec<(String,f64)>,    //desires while fleeing
+    pub idle: Vec<(String,f64)>,    //desires before noticing the player
+    pub flee_below: f64,            //fraction of its hit points below which it flees
+    pub sight: usize,       //how far it can see
+    pub light: usize,       //how far the light it carries shines
+    pub opens_doors: bool,  //whether it can open closed doors
+    pub breaks_doors: bool, //whether it smashes closed doors it cannot open
+    pub min_depth: usize,   //shallowest level the monster appears on
+    pub max_depth: usize,   //deepest level the monster appears on
+    pub frequency: u32,     //how often it appears compared to the other monsters of a level
+}
+
+impl MonsterDef {
+    /// A new creature of this type at the given position
+    pub fn create(&self, pos: Coord) -> Creature {
+        let mut creature = Creature::new(pos, &self.sprite, self.behavior);
+        creature.name = self.name.clone();
+        creature.glyph = self.glyph;
+        creature.speed = self.speed;
+        creature.hp = self.hp;
+        creature.max_hp = self.hp;
+        creature.attack = self.attack;
+        creature.defense = self.defense;
+        creature.desires = self.desires.clone();
+        creature.flee = self.flee.clone();
+        creature.idle = self.idle.clone();
+        creature.flee_below = self.flee_below;
+        creature.sight = self.sight;
+        creature.light = self.light;
+        creature.opens_doors = self.opens_doors;
+        creature.breaks_doors = self.breaks_doors;
+        creature
+    }
+}
+
+fn parse_desire(entry: &data::Entry, field: &data::Field) -> Result<(String,f64),DataError> {
+    let words: Vec<&str> = field.value.split_whitespace().collect();
+    match &words[..] {
+        [name, weight] => {
+            if !DESIRES.contains(name) {
+                return Err(entry.error(field.line, &format!("unknown desire '{}'; expected one of {}", name, DESIRES.join(", "))));
+            }
+            match weight.parse() {
+                Ok(weight) => Ok((name.to_string(), weight)),
+                Err(_) => Err(entry.error(field.line, &format!("'{}' is not a valid weight", weight))),
+            }
+        }
+        _ => Err(entry.error(field.line, &format!("expected '{} = <map> <weight>'", field.key))),
+    }
+}
+
+/// Read monster definitions from the text of a data file. `file` is only used in error messages.
+pub fn parse_monsters(file: &str, text: &str) -> Result<Vec<MonsterDef>,DataError> {
+    let mut monsters = vec![];
+    for entry in data::parse(file, text)? {
+        entry.check_keys(&["sprite","glyph","speed","hp","attack","defense","behavior","desire","flee","idle","flee_below","sight","light","opens_doors","breaks_doors","min_depth","max_depth","frequency"])?;
+
+        let glyph = entry.field("glyph")?;
+        let mut chars = glyph.value.chars();
+        let glyph = match (chars.next(), chars.next()) {
+            (Some(c), None) => c,
+            _ => return Err(entry.error(glyph.line, "glyph must be a single character")),
+        };
+        let behavior = entry.field("behavior")?;
+        let behavior = match Behavior::from_name(&behavior.value) {
+            Some(Behavior::Player) | None => return Err(entry.error(behavior.line, &format!("unknown behavior '{}'", behavior.value))),
+            Some(b) => b,
+        };
+        let mut desires = vec![];
+        for field in entry.all("desire") { desires.push(parse_desire(&entry, field)?) }
+        let mut flee = vec![];
+        for field in entry.all("flee") { flee.push(parse_desire(&entry, field)?) }
+        // Without instructions, fleeing creatures simply get away from the player
+        if flee.is_empty() { flee.push(("safety".to_string(), 1.0)) }
+        let mut idle = vec![];
+        for field in entry.all("idle") { idle.push(parse_desire(&entry, field)?) }
+
+        let monster = MonsterDef {
+            name: entry.name.clone(),
+            sprite: entry.field("sprite")?.value.clone(),
+            glyph: glyph,
+            speed: entry.value("speed")?,
+            hp: entry.value("hp")?,
+            attack: entry.value_or("attack", 0)?,
+            defense: entry.value_or("defense", 0)?,
+            behavior: behavior,
+            desires: desires,
+            flee: flee,
+            idle: idle,
+            flee_below: entry.value_or("flee_below", 0.0)?,
+            sight: entry.value_or("sight", DEFAULT_SIGHT)?,
+            light: entry.value_or("light", 0)?,
+            opens_doors: entry.value_or("opens_doors", false)?,
+            breaks_doors: entry.value_or("breaks_doors", false)?,
+            min_depth: entry.value_or("min_depth", 1)?,
+            max_depth: entry.value_or("max_depth", usize::MAX)?,
+            frequency: entry.value_or("frequency", 1)?,
+        };
+        // Catch values that parse but make no sense
+        if monster.speed <= 0 { return Err(entry.error(entry.field("speed")?.line, "speed must be positive")) }
+        if monster.hp <= 0 { return Err(entry.error(entry.field("hp")?.line, "hp must be positive")) }
+        if monster.sight == 0 { return Err(entry.error(entry.field("sight")?.line, "sight must be at least 1")) }
+        if monster.flee_below < 0.0 || monster.flee_below > 1.0 { return Err(entry.error(entry.field("flee_below")?.line, "flee_below must be between 0 and 1")) }
+        if monster.min_depth > monster.max_depth { return Err(entry.error(entry.line, "min_depth is deeper than max_depth")) }
+        monsters.push(monster);
+    }
+    Ok(monsters)
+}
+
+/// The monsters that come with the game
+pub fn builtin_monsters() -> Vec<MonsterDef> {
+    match parse_monsters("monsters.txt", include_str!("../assets/monsters.txt")) {
+        Ok(monsters) => monsters,
+        Err(e) => panic!("built-in monster data is invalid: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A valid monster, which the tests below break one line at a time
+    const GOBLIN: &str = "[goblin]\nsprite = goblin.png\nglyph = g\nspeed = 100\nhp = 10\nbehavior = Hunter\n";
+
+    /// The error parsing the goblin with one line replaced, as the line and entry it points at
+    fn error(from: &str, to: &str) -> (usize,Option<String>) {
+        assert!(GOBLIN.contains(from));
+        let e = parse_monsters("test.txt", &GOBLIN.replace(from, to)).unwrap_err();
+        (e.line, e.entry)
+    }
+
+    fn goblin(line: usize) -> (usize,Option<String>) { (line, Some("goblin".to_string())) }
+
+    #[test]
+    fn valid() { assert_eq!(parse_monsters("test.txt", GOBLIN).unwrap().len(), 1) }
+
+    #[test]
+    fn unknown_key() { assert_eq!(error("hp = 10", "hp = 10\nhitpoints = 10"), goblin(6)) }
+
+    #[test]
+    fn missing_key() { assert_eq!(error("speed = 100\n", ""), goblin(1)) }
+
+    #[test]
+    fn bad_glyph() {
+        assert_eq!(error("glyph = g", "glyph = gg"), goblin(3));
+        assert_eq!(error("glyph = g", "glyph ="), goblin(3));
+    }
+
+    #[test]
+    fn depths_out_of_order() { assert_eq!(error("hp = 10", "hp = 10\nmin_depth = 4\nmax_depth = 2"), goblin(1)) }
+
+    #[test]
+    fn bad_speed_and_hp() {
+        assert_eq!(error("speed = 100", "speed = 0"), goblin(4));
+        assert_eq!(error("speed = 100", "speed = fast"), goblin(4));
+        assert_eq!(error("hp = 10", "hp = -3"), goblin(5));
+    }
+
+    #[test]
+    fn blind() { assert_eq!(error("hp = 10", "hp = 10\nsight = 0"), goblin(6)) }
+
+    #[test]
+    fn unknown_desire() { assert_eq!(error("hp = 10", "hp = 10\ndesire = gold 1"), goblin(6)) }
+}
@@ -1,64 +1,153 @@
-use piston_window::*;
-use gfx_device_gl::{Resources, Output, CommandBuffer};
-use gfx_graphics::GfxGraphics;
-use sprite::Sprite;
 use dungeon::Map;
-use dijkstra_map::DijkstraMap;
+use grid::Coord;
 
+#[derive(Clone,Copy,Debug,PartialEq)]
 pub enum Behavior {
     Player,
-    Coward,
+    Coward,     //Runs from the player and hides
+    Hunter,     //Chases the player and attacks them
+    Wanderer,   //Roams from place to place, attacking the player if they get close
+    Pack,       //Hunts the player with others of its kind
+    Thief,      //Steals the player's gold and runs off with it
 }
 
+impl Behavior {
+    /// The name under which the behavior is stored in save files
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Behavior::Player => "Player",
+            Behavior::Coward => "Coward",
+            Behavior::Hunter => "Hunter",
+            Behavior::Wanderer => "Wanderer",
+            Behavior::Pack => "Pack",
+            Behavior::Thief => "Thief",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Behavior> {
+        match name {
+            "Player" => Some(Behavior::Player),
+            "Coward" => Some(Behavior::Coward),
+            "Hunter" => Some(Behavior::Hunter),
+            "Wanderer" => Some(Behavior::Wanderer),
+            "Pack" => Some(Behavior::Pack),
+            "Thief" => Some(Behavior::Thief),
+            _ => None,
+        }
+    }
+}
+
+/// What a creature knows of where the player is
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Awareness {
+    Unaware,            //has not noticed the player, and goes about its own business
+    Searching(Coord),   //has lost sight of the player, or heard them, and heads for where they were
+    Aware,              //has the player in sight
+}
+
+/// How far creatures see unless told otherwise
+pub const DEFAULT_SIGHT: usize = 8;
+
+/// Energy a creature must store up before it can act. Every tick of the game clock, each creature gains
+/// energy equal to its speed, so a creature of normal speed acts once every ten ticks.
+pub const ACTION_COST: i32 = 1000;
+pub const NORMAL_SPEED: i32 = 100;
+
 pub struct Creature {
     pub object: Object,
+    pub name: String,   //used for the creature in messages
+    pub glyph: char,    //used to draw the creature in the terminal
     pub ai: Behavior,
+    pub desires: Vec<(String,f64)>, //the dijkstra maps the creature moves along, with their weights
+    pub idle: Vec<(String,f64)>,    //the maps it moves along instead before it has noticed the player
+    pub flee: Vec<(String,f64)>,    //the maps it moves along instead when fleeing
+    pub flee_below: f64,            //fraction of its hit points below which it flees
+    pub speed: i32,     //energy gained per tick
+    pub energy: i32,    //energy stored up towards the next action
+    pub hp: i32,
+    pub max_hp: i32,
+    pub attack: i32,    //the most damage a blow can do
+    pub defense: i32,   //damage taken off every blow received
+    pub mana: i32,
+    pub max_mana: i32,
+    pub spells: Vec<String>,    //names of the spells the creature knows
+    pub fear: u32,      //number of actions for which the creature will flee
+    pub gold: u32,
+    pub goal: Option<Coord>,    //where a wandering creature is heading
+    pub sight: usize,   //how far it can see
+    pub light: usize,   //how far the light it carries shines, or 0 for none
+    pub stealth: u32,   //percent chance of going unnoticed by a creature at the edge of its sight
+    pub awareness: Awareness,
+    pub opens_doors: bool,  //whether it can open closed doors. The player always can, and can pick locks too.
+    pub breaks_doors: bool, //whether it smashes closed doors it cannot open
 }
 
 impl Creature {
-    pub fn new(pos:(usize,usize), w:&PistonWindow, graphic: &str, ai:Behavior) -> Creature {
+    pub fn new(pos:Coord, graphic: &str, ai:Behavior) -> Creature {
+        // Until told otherwise, a creature is named after its graphic
+        let name = match graphic.find('.') {
+            Some(n) => &graphic[..n],
+            None => graphic,
+        };
         Creature {
-            object: Object::new(pos,w,graphic),
+            object: Object::new(pos,graphic),
+            name: name.to_string(),
+            glyph: name.chars().next().unwrap_or('?'),
             ai: ai,
+            desires: vec![],
+            idle: vec![],
+            flee: vec![("safety".to_string(), 1.0)],
+            flee_below: 0.0,
+            speed: NORMAL_SPEED,
+            energy: 0,
+            hp: 10,
+            max_hp: 10,
+            attack: 2,
+            defense: 0,
+            mana: 0,
+            max_mana: 0,
+            spells: vec![],
+            fear: 0,
+            gold: 0,
+            goal: None,
+            sight: DEFAULT_SIGHT,
+            light: 0,
+            stealth: 0,
+            awareness: Awareness::Unaware,
+            opens_doors: false,
+            breaks_doors: false,
         }
     }
 
-    pub fn coordinates(&self) -> (usize,usize) {self.object.coordinates()}
+    pub fn can_act(&self) -> bool { self.energy >= ACTION_COST }
+    /// Pay for an action that takes the given fraction of a normal action's time
+    pub fn spend(&mut self, fraction: f64) { self.energy -= (ACTION_COST as f64 * fraction) as i32 }
+    pub fn tick(&mut self) { self.energy += self.speed }
+
+    pub fn coordinates(&self) -> Coord {self.object.coordinates()}
 }
 
 pub struct Object {
     pub i: usize,
     pub j: usize,
-    sprite: Sprite,
+    graphic: String,    //name of the image front-ends use to draw this object
 }
 
 impl Object {
 
-    pub fn new(pos:(usize,usize), w:&PistonWindow, filename: &str) -> Object {
-        Object {i : pos.0, j: pos.1, sprite: Sprite::new(w,filename)}
+    pub fn new(pos:Coord, graphic: &str) -> Object {
+        Object {i : pos.0, j: pos.1, graphic: graphic.to_string()}
     }
-    //Functions to translate between discrete and continuous coordinates. One cell is 32 units.
-    pub fn x(&self) -> f64 { (self.i as f64)*32.0 }
-    pub fn y(&self) -> f64 { (self.j as f64)*32.0 }
 
-    pub fn coordinates(&self) -> (usize,usize) {(self.i,self.j)}
+    pub fn coordinates(&self) -> Coord {(self.i,self.j)}
+    pub fn graphic(&self) -> &str { &self.graphic }
 
-    pub fn mov(&mut self, i:isize, j:isize) {
-        self.i = (self.i as isize + i) as usize;
-        self.j = (self.j as isize + j) as usize;
+    pub fn move_to(&mut self, c: Coord) {
+        self.i = c.0;
+        self.j = c.1;
     }
 
     pub fn visible(&self, map: &Map) -> bool {
-        map.tile(self.i,self.j).visible()
-    }
-
-    pub fn automove(&mut self, dmap: &DijkstraMap) {
-        let (i,j) = dmap.get_next_step((self.i,self.j));
-        self.i = i;
-        self.j = j;
-    }
-
-    pub fn render(&self, g: &mut GfxGraphics<Resources, CommandBuffer<Resources>, Output>, view: math::Matrix2d, map: &Map) {
-        if self.visible(map) {self.sprite.render(self.x(),self.y(),g,view)}
+        map.tile((self.i,self.j)).visible()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,117 @@
+//!Draws the game state in a piston window. Sprites are loaded the first time they are needed.
+
+use std::collections::HashMap;
+use piston_window::*;
+use gfx_device_gl::{Resources, Output, CommandBuffer};
+use gfx_graphics::GfxGraphics;
+use sprite::Sprite;
+use spellbound::game::Game;
+use spellbound::controls::Controls;
+use spellbound::dungeon::{LIGHT_FULL,Tile,TerrainType};
+use spellbound::object::Object;
+
+pub struct Renderer {
+    sprites: HashMap<String,Sprite>,
+}
+
+//Functions to translate between discrete and continuous coordinates. One cell is 32 units.
+fn x(i: usize) -> f64 { (i as f64)*32.0 }
+fn y(j: usize) -> f64 { (j as f64)*32.0 }
+
+fn tile_graphic(tile: &Tile) -> &'static str {
+    match *tile.terrain() {
+        TerrainType::Wall => "wall.png",
+        TerrainType::Floor => "floor.png",
+        TerrainType::Door => "door.png",
+        TerrainType::OpenDoor => "door_open.png",
+        TerrainType::LockedDoor => "door_locked.png",
+        TerrainType::Window => "window.png",
+        TerrainType::StairsUp => "stairs_up.png",
+        TerrainType::StairsDown => "stairs_down.png",
+        TerrainType::Torch => "torch.png",
+    }
+}
+
+impl Renderer {
+    pub fn new() -> Renderer {
+        Renderer { sprites: HashMap::new() }
+    }
+
+    // Make sure the sprite for the given image is loaded
+    fn load(&mut self, w: &PistonWindow, filename: &str) {
+        if !self.sprites.contains_key(filename) {
+            self.sprites.insert(filename.to_string(), Sprite::new(w,filename));
+        }
+    }
+
+    pub fn draw(&mut self, game: &Game, controls: &Controls, ren: RenderArgs, e: PistonWindow) {
+        // Load every sprite we will need before drawing
+        for filename in ["wall.png","floor.png","door.png","door_open.png","door_locked.png","window.png","stairs_up.png","stairs_down.png","torch.png","gold.png"].iter() { self.load(&e,filename) }
+        self.load(&e,game.player().object.graphic());
+        for creature in game.creatures().iter() { self.load(&e,creature.object.graphic()) }
+
+        let sprites = &self.sprites;
+        e.draw_2d(|c, g| {
+            clear([0.0, 0.0, 0.0, 1.0], g);
+            let (i0,j0) = game.player().coordinates();
+            let view = c.transform.trans((ren.width / 2) as f64-x(i0),(ren.height / 2) as f64-y(j0));
+
+            // render the map
+            for tile in game.map().grid.iter() {
+                let sprite = &sprites[tile_graphic(tile)];
+                //if is inlineofsight, shade it by how brightly it is lit
+                if tile.visible() {
+                    sprite.render(x(tile.i),y(tile.j),g,view);
+                    let shade = 0.6 * (1.0 - tile.light() as f32 / LIGHT_FULL as f32);
+                    rectangle([0.0, 0.0, 0.0, shade], rectangle::square(0.0, 0.0, 32.0), view.trans(x(tile.i),y(tile.j)), g);
+                }
+                //else if explored render with rectangle over it
+                else if tile.is_explored() {
+                    sprite.render(x(tile.i),y(tile.j),g,view);
+                    rectangle([0.0, 0.0, 0.0, 0.5], rectangle::square(0.0, 0.0, 32.0), view.trans(x(tile.i),y(tile.j)), g);
+                }
+            }
+            //render the gold the player can see
+            for &((i,j),_) in game.gold().iter() {
+                if game.map().tile((i,j)).visible() { sprites["gold.png"].render(x(i),y(j),g,view) }
+            }
+            //render the player
+            render_object(&game.player().object, sprites, g, view, game);
+
+            //render other creatures
+            for creature in game.creatures().iter() {
+                render_object(&creature.object, sprites, g, view, game);
+            }
+
+            //highlight the tile being aimed at
+            if let Some((i,j)) = controls.cursor() {
+                rectangle([1.0, 1.0, 0.0, 0.3], rectangle::square(0.0, 0.0, 32.0), view.trans(x(i),y(j)), g);
+            }
+
+            //mana bar along the top of the window
+            let player = game.player();
+            if player.max_mana > 0 {
+                let fraction = player.mana as f64 / player.max_mana as f64;
+                rectangle([0.2, 0.2, 0.2, 1.0], [0.0, 0.0, ren.width as f64, 6.0], c.transform, g);
+                rectangle([0.2, 0.4, 1.0, 1.0], [0.0, 0.0, ren.width as f64 * fraction, 6.0], c.transform, g);
+            }
+
+            //health bar below it
+            let fraction = player.hp.max(0) as f64 / player.max_hp as f64;
+            rectangle([0.2, 0.2, 0.2, 1.0], [0.0, 6.0, ren.width as f64, 6.0], c.transform, g);
+            rectangle([0.9, 0.1, 0.1, 1.0], [0.0, 6.0, ren.width as f64 * fraction, 6.0], c.transform, g);
+
+            //once the player is dead, the whole view fades to red
+            if game.is_over() {
+                rectangle([0.5, 0.0, 0.0, 0.6], [0.0, 0.0, ren.width as f64, ren.height as f64], c.transform, g);
+            }
+        });
+    }
+}
+
+fn render_object(object: &Object, sprites: &HashMap<String,Sprite>, g: &mut GfxGraphics<Resources, CommandBuffer<Resources>, Output>, view: math::Matrix2d, game: &Game) {
+    if object.visible(game.map()) {
+        let (i,j) = object.coordinates();
+        sprites[object.graphic()].render(x(i),y(j),g,view)
+    }
+}
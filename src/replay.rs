@@ -0,0 +1,249 @@
+//! Replays. Given its seed, choice of level generator and spell and monster definitions, a game only
+//! depends on the commands the player issues, so a replay stores the seed, the generator and every command
+//! that took a turn. Feeding the commands back through `Game::step` reproduces the run exactly, as long as
+//! the game is started with the spell and monster definitions it was recorded with. The replay keeps a hash
+//! of those definitions and refuses to play with any others.
+//!
+//! Replay files use the same plain text layout and errors as save files.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use game::{Command,Game};
+use monster::MonsterDef;
+use spell::SpellDef;
+use generator::GeneratorKind;
+use save::{SaveError,generator_name,generator_from_name};
+
+/// Increase this whenever the layout of a replay changes
+pub const REPLAY_VERSION: u32 = 3;
+
+pub struct Replay {
+    pub seed: u64,
+    pub generator: Option<GeneratorKind>,
+    pub data: u64,          //hash of the spell and monster definitions, from `data_hash`
+    pub commands: Vec<Command>,
+}
+
+/// A hash of spell and monster definitions, to tell whether a replay is played with the ones it was recorded
+/// with. This is FNV-1a over their debug output, which unlike the standard library's hasher is the same on
+/// every build.
+pub fn data_hash(spells: &[SpellDef], monsters: &[MonsterDef]) -> u64 {
+    let text = format!("{:?}{:?}", spells, monsters);
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn command_code(command: &Command) -> Option<String> {
+    match *command {
+        Command::None => None,
+        Command::Move(i,j) => Some(format!("move {} {}", i, j)),
+        Command::Automove => Some("automove".to_string()),
+        Command::Cast(n,(i,j)) => Some(format!("cast {} {} {}", n, i, j)),
+        Command::Descend => Some("descend".to_string()),
+        Command::Ascend => Some("ascend".to_string()),
+        Command::Open(i,j) => Some(format!("open {} {}", i, j)),
+        Command::Close(i,j) => Some(format!("close {} {}", i, j)),
+    }
+}
+
+fn command_from_code(code: &str) -> Option<Command> {
+    let words: Vec<&str> = code.split_whitespace().collect();
+    match &words[..] {
+        ["move", i, j] => match (i.parse(), j.parse()) {
+            (Ok(i), Ok(j)) => Some(Command::Move(i,j)),
+            _ => None,
+        },
+        ["open", i, j] => match (i.parse(), j.parse()) {
+            (Ok(i), Ok(j)) => Some(Command::Open(i,j)),
+            _ => None,
+        },
+        ["close", i, j] => match (i.parse(), j.parse()) {
+            (Ok(i), Ok(j)) => Some(Command::Close(i,j)),
+            _ => None,
+        },
+        ["automove"] => Some(Command::Automove),
+        ["descend"] => Some(Command::Descend),
+        ["ascend"] => Some(Command::Ascend),
+        ["cast", n, i, j] => match (n.parse(), i.parse(), j.parse()) {
+            (Ok(n), Ok(i), Ok(j)) => Some(Command::Cast(n,(i,j))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl Replay {
+    /// Start recording a new game with the given seed, generator and spell and monster definitions
+    pub fn new(seed: u64, generator: Option<GeneratorKind>, spells: &[SpellDef], monsters: &[MonsterDef]) -> Replay {
+        Replay { seed: seed, generator: generator, data: data_hash(spells, monsters), commands: vec![] }
+    }
+
+    /// Record a command. Only commands that took a turn need to be recorded.
+    pub fn record(&mut self, command: Command) {
+        if command != Command::None { self.commands.push(command) }
+    }
+
+    /// Create the game the replay starts from, with the given spell and monster definitions. They must be the
+    /// ones the replay was recorded with.
+    pub fn start(&self, spells: Vec<SpellDef>, monsters: Vec<MonsterDef>) -> Result<Game,SaveError> {
+        if data_hash(&spells, &monsters) != self.data { return Err(SaveError::Data) }
+        let mut game = Game::with_generator(self.seed, self.generator);
+        game.set_spells(spells);
+        game.set_monsters(monsters);
+        Ok(game)
+    }
+
+    /// Run the whole replay without any front-end, with the given spell and monster definitions, and return
+    /// the final state of the game
+    pub fn play(&self, spells: Vec<SpellDef>, monsters: Vec<MonsterDef>) -> Result<Game,SaveError> {
+        let mut game = self.start(spells, monsters)?;
+        for command in self.commands.iter() { game.step(*command); }
+        Ok(game)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(),SaveError> {
+        let mut out = String::new();
+        out.push_str(&format!("spellbound replay {}\n", REPLAY_VERSION));
+        out.push_str(&format!("seed {}\n", self.seed));
+        out.push_str(&format!("generator {}\n", generator_name(self.generator)));
+        out.push_str(&format!("data {:016x}\n", self.data));
+        for command in self.commands.iter() {
+            if let Some(code) = command_code(command) {
+                out.push_str(&code);
+                out.push('\n');
+            }
+        }
+        let mut file = File::create(path)?;
+        file.write_all(out.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Replay,SaveError> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+        let mut lines = text.lines().enumerate();
+
+        let corrupt = |n: usize, msg: &str| SaveError::Corrupt(n+1, msg.to_string());
+        // Check the version before anything else
+        match lines.next() {
+            Some((n,line)) => {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                match &words[..] {
+                    ["spellbound", "replay", version] => match version.parse() {
+                        Ok(REPLAY_VERSION) => (),
+                        Ok(version) => return Err(SaveError::Version(version)),
+                        Err(_) => return Err(corrupt(n,"version is not a number")),
+                    },
+                    _ => return Err(corrupt(n,"not a spellbound replay file")),
+                }
+            }
+            None => return Err(corrupt(0,"file is empty")),
+        }
+        let seed = match lines.next() {
+            Some((n,line)) => {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                match &words[..] {
+                    ["seed", seed] => match seed.parse() {
+                        Ok(seed) => seed,
+                        Err(_) => return Err(corrupt(n,"seed is not a number")),
+                    },
+                    _ => return Err(corrupt(n,"expected 'seed'")),
+                }
+            }
+            None => return Err(corrupt(1,"unexpected end of file")),
+        };
+        let generator = match lines.next() {
+            Some((n,line)) => {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                match &words[..] {
+                    ["generator", name] => match generator_from_name(name) {
+                        Some(generator) => generator,
+                        None => return Err(corrupt(n,&format!("unknown generator '{}'", name))),
+                    },
+                    _ => return Err(corrupt(n,"expected 'generator'")),
+                }
+            }
+            None => return Err(corrupt(2,"unexpected end of file")),
+        };
+        let data = match lines.next() {
+            Some((n,line)) => {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                match &words[..] {
+                    ["data", hash] => match u64::from_str_radix(hash, 16) {
+                        Ok(hash) => hash,
+                        Err(_) => return Err(corrupt(n,"data hash is not a hexadecimal number")),
+                    },
+                    _ => return Err(corrupt(n,"expected 'data'")),
+                }
+            }
+            None => return Err(corrupt(3,"unexpected end of file")),
+        };
+        let mut commands = vec![];
+        for (n,line) in lines {
+            match command_from_code(line) {
+                Some(command) => commands.push(command),
+                None => return Err(corrupt(n,&format!("unknown command '{}'", line))),
+            }
+        }
+        Ok(Replay { seed: seed, generator: generator, data: data, commands: commands })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+    use monster::builtin_monsters;
+    use save;
+    use spell::builtin_spells;
+
+    fn temp_path(name: &str) -> PathBuf { env::temp_dir().join(format!("spellbound-test-{}", name)) }
+
+    /// The text of a save of the game, to compare whole states
+    fn saved(game: &Game, name: &str) -> String {
+        let path = temp_path(name);
+        save::save(game, &path).unwrap();
+        let mut text = String::new();
+        File::open(&path).unwrap().read_to_string(&mut text).unwrap();
+        text
+    }
+
+    /// Play a game with a mix of commands, recording the ones that took a turn
+    fn recorded(seed: u64) -> (Game,Replay) {
+        let mut game = Game::new(seed);
+        let mut replay = Replay::new(seed, None, &builtin_spells(), &builtin_monsters());
+        for n in 0..60 {
+            let command = match n % 4 {
+                0 => Command::Move(1,0),
+                1 => Command::Move(0,1),
+                _ => Command::Automove,
+            };
+            if game.step(command) { replay.record(command) }
+        }
+        (game,replay)
+    }
+
+    #[test]
+    fn replay_reproduces_the_game() {
+        let (game, replay) = recorded(11);
+        let path = temp_path("replay.replay");
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path).unwrap();
+        assert_eq!(loaded.commands, replay.commands);
+        let played = loaded.play(builtin_spells(), builtin_monsters()).unwrap();
+        assert_eq!(saved(&played, "replay-played.sav"), saved(&game, "replay-recorded.sav"));
+    }
+
+    #[test]
+    fn other_data_is_rejected() {
+        let (_, replay) = recorded(11);
+        let mut spells = builtin_spells();
+        spells[0].cost += 1;
+        match replay.start(spells, builtin_monsters()) {
+            Err(SaveError::Data) => (),
+            Err(e) => panic!("expected a data error, got: {}", e),
+            Ok(_) => panic!("a replay was started with other spells"),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! The game's random number generator. Everything random in a run (map generation, spawning, ai) draws
+//! from a single generator owned by the game, so a run is fully determined by its seed.
+
+use rand::Rng;
+
+/// A xorshift128 generator. Its state is small and exposed so that it can be stored and restored.
+#[derive(Clone)]
+pub struct GameRng { state: [u32;4] }
+
+impl GameRng {
+	pub fn new(seed: u64) -> GameRng {
+		// Spread the seed over the whole state with splitmix64, so that nearby seeds give unrelated runs
+		let mut z = seed;
+		let mut state = [0u32;4];
+		for n in 0..2 {
+			z = z.wrapping_add(0x9E3779B97F4A7C15);
+			let mut x = z;
+			x = (x ^ (x >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+			x = (x ^ (x >> 27)).wrapping_mul(0x94D049BB133111EB);
+			x = x ^ (x >> 31);
+			state[2*n] = x as u32;
+			state[2*n+1] = (x >> 32) as u32;
+		}
+		GameRng::from_state(state)
+	}
+
+	/// Rebuild a generator from a saved state
+	pub fn from_state(state: [u32;4]) -> GameRng {
+		// xorshift never leaves the all-zero state, so avoid it
+		match state {
+			[0,0,0,0] => GameRng { state: [1,0,0,0] },
+			_ => GameRng { state: state },
+		}
+	}
+
+	pub fn state(&self) -> [u32;4] { self.state }
+}
+
+impl Rng for GameRng {
+	fn next_u32(&mut self) -> u32 {
+		let t = self.state[0] ^ (self.state[0] << 11);
+		self.state[0] = self.state[1];
+		self.state[1] = self.state[2];
+		self.state[2] = self.state[3];
+		self.state[3] = self.state[3] ^ (self.state[3] >> 19) ^ (t ^ (t >> 8));
+		self.state[3]
+	}
+}
@@ -0,0 +1,403 @@
+//! Saving and loading games.
+//!
+//! Saves are plain text, one record per line, so they can be inspected by hand. The first line holds the
+//! format version. A save written with a different version is rejected instead of being misread.
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use dungeon::{LIGHT_FULL,Map,TerrainType};
+use object::{Awareness,Behavior,Creature};
+use game::{Game,Level};
+use generator::GeneratorKind;
+use rng::GameRng;
+use grid::{Coord,Grid};
+
+/// Increase this whenever the layout of a save changes
+pub const SAVE_VERSION: u32 = 12;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    /// The save was written by an incompatible version of the game. Holds the version of the save.
+    Version(u32),
+    /// The save could not be understood. Holds the line number and a description of the problem.
+    Corrupt(usize,String),
+    /// A replay was recorded with other spell or monster definitions than the ones it is played with
+    Data,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SaveError::Io(ref e) => write!(f, "could not access save file: {}", e),
+            SaveError::Version(v) => write!(f, "save file has version {}, but this game only reads version {}", v, SAVE_VERSION),
+            SaveError::Corrupt(line,ref msg) => write!(f, "save file is corrupt at line {}: {}", line, msg),
+            SaveError::Data => write!(f, "replay was recorded with different spell or monster definitions"),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> SaveError { SaveError::Io(e) }
+}
+
+/// Write the full state of a game to the given file
+pub fn save(game: &Game, path: &Path) -> Result<(),SaveError> {
+    let mut out = String::new();
+    out.push_str(&format!("spellbound save {}\n", SAVE_VERSION));
+    out.push_str(&format!("seed {}\n", game.seed()));
+    let rng = game.rng().state();
+    out.push_str(&format!("rng {} {} {} {}\n", rng[0], rng[1], rng[2], rng[3]));
+    out.push_str(&format!("turn {}\n", game.turn()));
+    out.push_str(&format!("time {}\n", game.time()));
+    out.push_str(&format!("generator {}\n", generator_name(game.generator())));
+
+    // Every level generated so far, from the top down
+    out.push_str(&format!("depth {}\n", game.depth()));
+    out.push_str(&format!("levels {}\n", game.level_count()));
+    for depth in 1..game.level_count()+1 {
+        out.push_str(&format!("level {}\n", depth));
+        match game.level(depth) {
+            Some(level) => write_level(&mut out, &level.map, &level.creatures, &level.gold),
+            None => write_level(&mut out, game.map(), game.creatures(), game.gold()),
+        }
+    }
+
+    let (i,j) = game.player().coordinates();
+    let player = game.player();
+    out.push_str(&format!("player {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}\n", i, j, player.object.graphic(), player.speed, player.energy, player.hp, player.max_hp, player.attack, player.defense, player.mana, player.max_mana, player.gold, player.sight, player.stealth, player.light));
+    out.push_str(&format!("spells {}\n", player.spells.join(" ")));
+
+    let mut file = File::create(path)?;
+    file.write_all(out.as_bytes())?;
+    Ok(())
+}
+
+/// Write the terrain, exploration memory and light left by spells of a level, one line per row each, then
+/// what lies on it
+fn write_level(out: &mut String, map: &Map, creatures: &[Creature], gold: &[(Coord,u32)]) {
+    out.push_str(&format!("map {} {} {}\n", map.width(), map.height(), map.ambient()));
+    for row in map.grid.rows() {
+        let line: String = row.iter().map(|tile| tile.terrain().glyph()).collect();
+        out.push_str(&line);
+        out.push('\n');
+    }
+    for row in map.grid.rows() {
+        let line: String = row.iter().map(|tile| if tile.is_explored() { '1' } else { '0' }).collect();
+        out.push_str(&line);
+        out.push('\n');
+    }
+    for row in map.grid.rows() {
+        let line: String = row.iter().map(|tile| (b'0' + tile.magic()) as char).collect();
+        out.push_str(&line);
+        out.push('\n');
+    }
+
+    out.push_str(&format!("gold {}\n", gold.len()));
+    for &((i,j),amount) in gold.iter() { out.push_str(&format!("pile {} {} {}\n", i, j, amount)) }
+
+    out.push_str(&format!("creatures {}\n", creatures.len()));
+    for creature in creatures.iter() {
+        let (i,j) = creature.coordinates();
+        out.push_str(&format!("creature {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}\n", i, j, creature.object.graphic(), creature.glyph, creature.ai.name(), creature.speed, creature.energy, creature.hp, creature.max_hp, creature.attack, creature.defense, creature.fear, creature.gold, creature.flee_below, creature.sight, creature.light, creature.opens_doors, creature.breaks_doors));
+        out.push_str(&format!("name {}\n", creature.name));
+        out.push_str(&format!("desires {}\n", desire_list(&creature.desires)));
+        out.push_str(&format!("flee {}\n", desire_list(&creature.flee)));
+        out.push_str(&format!("idle {}\n", desire_list(&creature.idle)));
+        match creature.goal {
+            Some((i,j)) => out.push_str(&format!("goal {} {}\n", i, j)),
+            None => out.push_str("goal\n"),
+        }
+        match creature.awareness {
+            Awareness::Unaware => out.push_str("awareness unaware\n"),
+            Awareness::Searching((i,j)) => out.push_str(&format!("awareness searching {} {}\n", i, j)),
+            Awareness::Aware => out.push_str("awareness aware\n"),
+        }
+    }
+}
+
+/// The generator a game uses, as stored in saves and replays. "depth" stands for none, where the generator
+/// changes with depth.
+pub fn generator_name(generator: Option<GeneratorKind>) -> &'static str {
+    match generator {
+        Some(kind) => kind.name(),
+        None => "depth",
+    }
+}
+
+/// Read a generator written by `generator_name`. The outer None means the name is unknown.
+pub fn generator_from_name(name: &str) -> Option<Option<GeneratorKind>> {
+    match name {
+        "depth" => Some(None),
+        _ => GeneratorKind::from_name(name).map(Some),
+    }
+}
+
+// Desires are stored as pairs of map name and weight
+fn desire_list(desires: &[(String,f64)]) -> String {
+    let pairs: Vec<String> = desires.iter().map(|&(ref map, weight)| format!("{} {}", map, weight)).collect();
+    pairs.join(" ")
+}
+
+/// Reads a save line by line, keeping track of the line number for error messages
+struct Reader<'a> {
+    lines: Vec<&'a str>,
+    line: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn error(&self, msg: &str) -> SaveError { SaveError::Corrupt(self.line, msg.to_string()) }
+
+    fn next(&mut self) -> Result<&'a str,SaveError> {
+        match self.lines.get(self.line) {
+            Some(line) => {
+                self.line += 1;
+                Ok(line)
+            }
+            None => Err(SaveError::Corrupt(self.line+1, "unexpected end of file".to_string())),
+        }
+    }
+
+    /// Read a line of the form `key value value ...` and return the values
+    fn fields(&mut self, key: &str, count: usize) -> Result<Vec<&'a str>,SaveError> {
+        let line = self.next()?;
+        let mut words = line.split_whitespace();
+        if words.next() != Some(key) { return Err(self.error(&format!("expected '{}'", key))) }
+        let values: Vec<&str> = words.collect();
+        if values.len() != count { return Err(self.error(&format!("'{}' needs {} values", key, count))) }
+        Ok(values)
+    }
+
+    /// Read a line of the form `key value value ...` with any number of values
+    fn list(&mut self, key: &str) -> Result<Vec<&'a str>,SaveError> {
+        let line = self.next()?;
+        let mut words = line.split_whitespace();
+        if words.next() != Some(key) { return Err(self.error(&format!("expected '{}'", key))) }
+        Ok(words.collect())
+    }
+
+    /// Read a line of desires written by `desire_list`
+    fn desires(&mut self, key: &str) -> Result<Vec<(String,f64)>,SaveError> {
+        let values = self.list(key)?;
+        if values.len() % 2 != 0 { return Err(self.error("each desire needs a map and a weight")) }
+        let mut desires = vec![];
+        for pair in values.chunks(2) { desires.push((pair[0].to_string(), self.parse(pair[1])?)) }
+        Ok(desires)
+    }
+
+    fn parse<T: FromStr>(&self, value: &str) -> Result<T,SaveError> {
+        value.parse().map_err(|_| self.error(&format!("'{}' is not a valid number", value)))
+    }
+
+    /// Read a line of the form `key value` and parse the value
+    fn value<T: FromStr>(&mut self, key: &str) -> Result<T,SaveError> {
+        let values = self.fields(key, 1)?;
+        self.parse(values[0])
+    }
+
+    /// Read a level written by `write_level`
+    fn level(&mut self) -> Result<Level,SaveError> {
+        let values = self.fields("map", 3)?;
+        let (width, height): (usize,usize) = (self.parse(values[0])?, self.parse(values[1])?);
+        let ambient: u8 = self.parse(values[2])?;
+        if ambient > LIGHT_FULL { return Err(self.error("ambient light is too bright")) }
+        let mut terrain = vec![];
+        for _ in 0..height {
+            let line = self.next()?;
+            let mut row = vec![];
+            for code in line.chars() {
+                match TerrainType::from_glyph(code) {
+                    Some(t) => row.push(t),
+                    None => return Err(self.error(&format!("unknown terrain '{}'", code))),
+                }
+            }
+            if row.len() != width { return Err(self.error("map row has the wrong width")) }
+            terrain.push(row);
+        }
+        let mut map = Map::from_terrain(Grid::from_rows(terrain));
+        for j in 0..height {
+            let line = self.next()?;
+            if line.len() != width { return Err(self.error("exploration row has the wrong width")) }
+            for (i,code) in line.chars().enumerate() {
+                match code {
+                    '1' => map.tile_mut((i,j)).explore(),
+                    '0' => (),
+                    _ => return Err(self.error("exploration rows may only contain 0 and 1")),
+                }
+            }
+        }
+        for j in 0..height {
+            let line = self.next()?;
+            if line.len() != width { return Err(self.error("light row has the wrong width")) }
+            for (i,code) in line.chars().enumerate() {
+                match code.to_digit(10) {
+                    Some(light) if light <= LIGHT_FULL as u32 => map.tile_mut((i,j)).set_magic(light as u8),
+                    _ => return Err(self.error(&format!("light rows may only contain digits up to {}", LIGHT_FULL))),
+                }
+            }
+        }
+        map.set_ambient(ambient);
+
+        let count: usize = self.value("gold")?;
+        let mut gold = vec![];
+        for _ in 0..count {
+            let values = self.fields("pile", 3)?;
+            let pos = (self.parse(values[0])?, self.parse(values[1])?);
+            if pos.0 >= width || pos.1 >= height { return Err(self.error("gold is outside the map")) }
+            gold.push((pos, self.parse(values[2])?));
+        }
+
+        let count: usize = self.value("creatures")?;
+        let mut creatures = vec![];
+        for _ in 0..count {
+            let values = self.fields("creature", 18)?;
+            let pos = (self.parse(values[0])?, self.parse(values[1])?);
+            if pos.0 >= width || pos.1 >= height { return Err(self.error("creature is outside the map")) }
+            let ai = match Behavior::from_name(values[4]) {
+                Some(ai) => ai,
+                None => return Err(self.error(&format!("unknown behavior '{}'", values[4]))),
+            };
+            let mut creature = Creature::new(pos, values[2], ai);
+            creature.glyph = self.parse(values[3])?;
+            creature.speed = self.parse(values[5])?;
+            creature.energy = self.parse(values[6])?;
+            creature.hp = self.parse(values[7])?;
+            creature.max_hp = self.parse(values[8])?;
+            creature.attack = self.parse(values[9])?;
+            creature.defense = self.parse(values[10])?;
+            creature.fear = self.parse(values[11])?;
+            creature.gold = self.parse(values[12])?;
+            creature.flee_below = self.parse(values[13])?;
+            creature.sight = self.parse(values[14])?;
+            creature.light = self.parse(values[15])?;
+            creature.opens_doors = self.parse(values[16])?;
+            creature.breaks_doors = self.parse(values[17])?;
+            creature.name = self.list("name")?.join(" ");
+            creature.desires = self.desires("desires")?;
+            creature.flee = self.desires("flee")?;
+            creature.idle = self.desires("idle")?;
+            let goal = self.list("goal")?;
+            creature.goal = match &goal[..] {
+                [] => None,
+                [i, j] => Some((self.parse(i)?, self.parse(j)?)),
+                _ => return Err(self.error("'goal' needs no values or 2")),
+            };
+            creature.awareness = match &self.list("awareness")?[..] {
+                ["unaware"] => Awareness::Unaware,
+                ["searching", i, j] => Awareness::Searching((self.parse(i)?, self.parse(j)?)),
+                ["aware"] => Awareness::Aware,
+                _ => return Err(self.error("'awareness' must be unaware, aware or searching with a tile")),
+            };
+            creatures.push(creature);
+        }
+
+        Ok(Level { map: map, creatures: creatures, gold: gold })
+    }
+}
+
+/// Read a game from the given file
+pub fn load(path: &Path) -> Result<Game,SaveError> {
+    let mut text = String::new();
+    File::open(path)?.read_to_string(&mut text)?;
+    let mut r = Reader { lines: text.lines().collect(), line: 0 };
+
+    // Check the version before anything else, since the rest of the layout depends on it
+    let header = r.fields("spellbound", 2)?;
+    if header[0] != "save" { return Err(r.error("not a spellbound save file")) }
+    let version: u32 = r.parse(header[1])?;
+    if version != SAVE_VERSION { return Err(SaveError::Version(version)) }
+
+    let seed: u64 = r.value("seed")?;
+    let mut rng = [0u32;4];
+    let values = r.fields("rng", 4)?;
+    for n in 0..4 { rng[n] = r.parse(values[n])? }
+    let turn: u64 = r.value("turn")?;
+    let time: u64 = r.value("time")?;
+    let values = r.fields("generator", 1)?;
+    let generator = match generator_from_name(values[0]) {
+        Some(generator) => generator,
+        None => return Err(r.error(&format!("unknown generator '{}'", values[0]))),
+    };
+
+    let depth: usize = r.value("depth")?;
+    let count: usize = r.value("levels")?;
+    if depth == 0 || depth > count { return Err(r.error("the player is on a level that does not exist")) }
+    let mut levels = vec![];
+    for n in 1..count+1 {
+        if r.value::<usize>("level")? != n { return Err(r.error(&format!("expected level {}", n))) }
+        levels.push(r.level()?);
+    }
+    let (width, height) = (levels[depth-1].map.width(), levels[depth-1].map.height());
+
+    let values = r.fields("player", 15)?;
+    let pos = (r.parse(values[0])?, r.parse(values[1])?);
+    if pos.0 >= width || pos.1 >= height { return Err(r.error("player is outside the map")) }
+    let mut player = Creature::new(pos, values[2], Behavior::Player);
+    player.speed = r.parse(values[3])?;
+    player.energy = r.parse(values[4])?;
+    player.hp = r.parse(values[5])?;
+    player.max_hp = r.parse(values[6])?;
+    player.attack = r.parse(values[7])?;
+    player.defense = r.parse(values[8])?;
+    player.mana = r.parse(values[9])?;
+    player.max_mana = r.parse(values[10])?;
+    player.gold = r.parse(values[11])?;
+    player.sight = r.parse(values[12])?;
+    player.stealth = r.parse(values[13])?;
+    player.light = r.parse(values[14])?;
+    player.spells = r.list("spells")?.iter().map(|name| name.to_string()).collect();
+
+    Ok(Game::restore(seed, GameRng::from_state(rng), turn, time, generator, depth, levels, player))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+    use game::Command;
+
+    fn temp_path(name: &str) -> PathBuf { env::temp_dir().join(format!("spellbound-test-{}.sav", name)) }
+
+    /// Save a game and return the text of the save
+    fn saved(game: &Game, name: &str) -> String {
+        let path = temp_path(name);
+        save(game, &path).unwrap();
+        let mut text = String::new();
+        File::open(&path).unwrap().read_to_string(&mut text).unwrap();
+        text
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut game = Game::new(3);
+        for _ in 0..30 { game.step(Command::Automove); }
+        let text = saved(&game, "round-trip");
+        let mut loaded = load(&temp_path("round-trip")).unwrap();
+        assert_eq!(saved(&loaded, "round-trip-loaded"), text);
+
+        // The loaded game carries on exactly like the original
+        for _ in 0..30 {
+            game.step(Command::Automove);
+            loaded.step(Command::Automove);
+        }
+        assert_eq!(saved(&loaded, "round-trip-loaded"), saved(&game, "round-trip"));
+    }
+
+    #[test]
+    fn other_version_is_rejected() {
+        let text = saved(&Game::new(3), "version");
+        let bumped = text.replacen(&format!("spellbound save {}", SAVE_VERSION), &format!("spellbound save {}", SAVE_VERSION+1), 1);
+        let path = temp_path("version");
+        File::create(&path).unwrap().write_all(bumped.as_bytes()).unwrap();
+        match load(&path) {
+            Err(SaveError::Version(v)) => assert_eq!(v, SAVE_VERSION+1),
+            Err(e) => panic!("expected a version error, got: {}", e),
+            Ok(_) => panic!("a save with another version was loaded"),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Spells. The spells themselves are data, read from `assets/spells.txt`; this module only knows about the
+//! kinds of target a spell can have and the basic effects spells are built from.
+
+use std::str::FromStr;
+use data;
+use data::DataError;
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Targeting {
+    Caster,     //the spell always affects the caster's own tile
+    Creature,   //a visible creature
+    Tile,       //a visible, empty tile
+    Terrain,    //any visible tile, walls included
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Effect {
+    Damage(u32),    //the creature at the target loses health
+    Teleport,       //the caster moves to the target
+    Light(usize),   //reveal the tiles the target can see within a radius
+    Fear(u32),      //the creature at the target flees for a number of actions
+    Dig,            //a wall at the target crumbles to floor
+    Shatter,        //a window at the target shatters, leaving floor
+    Burn,           //a door at the target burns away, leaving floor
+}
+
+#[derive(Clone,Debug)]
+pub struct SpellDef {
+    pub name: String,
+    pub cost: i32,
+    pub range: usize,
+    pub target: Targeting,
+    pub effects: Vec<Effect>,
+}
+
+/// Read the number of an effect as the type the effect holds. None of them can be negative.
+fn number<T: FromStr>(entry: &data::Entry, field: &data::Field, word: &str) -> Result<T,DataError> {
+    word.parse().map_err(|_| entry.error(field.line, &format!("'{}' is not a whole number of at least 0", word)))
+}
+
+fn parse_effect(entry: &data::Entry, field: &data::Field) -> Result<Effect,DataError> {
+    let words: Vec<&str> = field.value.split_whitespace().collect();
+    match &words[..] {
+        ["damage", n] => Ok(Effect::Damage(number(entry, field, n)?)),
+        ["teleport"] => Ok(Effect::Teleport),
+        ["light", r] => Ok(Effect::Light(number(entry, field, r)?)),
+        ["fear", n] => Ok(Effect::Fear(number(entry, field, n)?)),
+        ["dig"] => Ok(Effect::Dig),
+        ["shatter"] => Ok(Effect::Shatter),
+        ["burn"] => Ok(Effect::Burn),
+        _ => Err(entry.error(field.line, &format!("unknown effect '{}'", field.value))),
+    }
+}
+
+/// Read spell definitions from the text of a data file. `file` is only used in error messages.
+pub fn parse_spells(file: &str, text: &str) -> Result<Vec<SpellDef>,DataError> {
+    let mut spells = vec![];
+    for entry in data::parse(file, text)? {
+        // Saves list the player's spells by name, separated by spaces
+        if entry.name.is_empty() || entry.name.contains(char::is_whitespace) {
+            return Err(entry.error(entry.line, "a spell name must be one word"));
+        }
+        entry.check_keys(&["cost","range","target","effect"])?;
+        let target = entry.field("target")?;
+        let target = match &target.value[..] {
+            "self" => Targeting::Caster,
+            "creature" => Targeting::Creature,
+            "tile" => Targeting::Tile,
+            "terrain" => Targeting::Terrain,
+            _ => return Err(entry.error(target.line, "target must be self, creature, tile or terrain")),
+        };
+        let mut effects = vec![];
+        for field in entry.all("effect") { effects.push(parse_effect(&entry, field)?) }
+        if effects.is_empty() { return Err(entry.error(entry.line, "a spell needs at least one effect")) }
+        // A spell with a negative cost would give mana instead
+        let cost = entry.value("cost")?;
+        if cost < 0 { return Err(entry.error(entry.field("cost")?.line, "cost must be at least 0")) }
+        spells.push(SpellDef {
+            name: entry.name.clone(),
+            cost: cost,
+            range: entry.value_or("range", 0)?,
+            target: target,
+            effects: effects,
+        });
+    }
+    Ok(spells)
+}
+
+/// The spells that come with the game
+pub fn builtin_spells() -> Vec<SpellDef> {
+    match parse_spells("spells.txt", include_str!("../assets/spells.txt")) {
+        Ok(spells) => spells,
+        Err(e) => panic!("built-in spell data is invalid: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spell_names_are_one_word() {
+        let e = parse_spells("test.txt", "[fire bolt]\ncost = 3\ntarget = creature\neffect = damage 6\n").unwrap_err();
+        assert_eq!((e.line, e.entry), (1, Some("fire bolt".to_string())));
+    }
+
+    #[test]
+    fn cost_is_not_negative() {
+        let e = parse_spells("test.txt", "[bolt]\ntarget = creature\ncost = -3\neffect = damage 6\n").unwrap_err();
+        assert_eq!((e.line, e.entry), (3, Some("bolt".to_string())));
+    }
+}
@@ -0,0 +1,207 @@
+//! Terminal front-end. Draws the game as characters and reads keys straight from stdin, so it can be
+//! played over ssh or on machines without a GPU. It uses the same keys as the window: arrows or the
+//! number keys to move, `o` to explore automatically, `>` and `<` to take the stairs down and up, `e` and
+//! `c` followed by a direction to open and close a door, `z` to cast a spell and escape to quit. While
+//! aiming, tab jumps between creatures, enter casts and escape cancels.
+
+use std::io::{Read, Write};
+use std::process::{Command as Shell, Stdio};
+use std::time::Instant;
+use spellbound::game::Game;
+use spellbound::controls::{Controls,Key,Mode};
+use spellbound::dungeon::TerrainType;
+use spellbound::replay::Replay;
+
+// Size of the part of the map shown around the player
+const VIEW_WIDTH: isize = 79;
+const VIEW_HEIGHT: isize = 22;
+
+// Tiles in view lit less brightly than this are drawn grey
+const DIM_LIGHT: u8 = 4;
+
+/// Puts the terminal in raw mode for as long as it lives, and restores the previous settings afterwards
+struct RawMode { saved: String }
+
+fn stty(args: &[&str]) -> Option<String> {
+    Shell::new("stty").args(args).stdin(Stdio::inherit()).output().ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+impl RawMode {
+    fn new() -> RawMode {
+        let saved = stty(&["-g"]).unwrap_or("sane".to_string());
+        // Reads return after a tenth of a second even if no key was pressed. This lets us tell a lone
+        // escape from the start of an arrow key, and keeps replays running without input.
+        stty(&["raw","-echo","min","0","time","1"]);
+        RawMode { saved: saved }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&[&self.saved]);
+        print!("\x1b[0m\x1b[2J\x1b[H");
+        let _ = ::std::io::stdout().flush();
+    }
+}
+
+enum Input {
+    Key(Key),
+    Escape,
+    Nothing,
+}
+
+// Read one key press from stdin
+fn read_key() -> Input {
+    let mut stdin = ::std::io::stdin();
+    let mut buffer = [0u8;8];
+    let n = match stdin.read(&mut buffer) {
+        Ok(n) => n,
+        Err(_) => return Input::Escape,
+    };
+    match &buffer[..n] {
+        //Arrow keys
+        b"\x1b[A" => Input::Key(Key::Direction(0,-1)),
+        b"\x1b[B" => Input::Key(Key::Direction(0,1)),
+        b"\x1b[D" => Input::Key(Key::Direction(-1,0)),
+        b"\x1b[C" => Input::Key(Key::Direction(1,0)),
+        //Number keys move as on the numpad, or pick from menus
+        &[digit] if (b'1'..=b'9').contains(&digit) => Input::Key(Key::Number((digit - b'0') as u32)),
+        //Automated movement
+        b"o" => Input::Key(Key::Automove),
+        //Stairs
+        b">" => Input::Key(Key::Descend),
+        b"<" => Input::Key(Key::Ascend),
+        //Spells
+        b"z" => Input::Key(Key::Cast),
+        b"\t" => Input::Key(Key::NextTarget),
+        b"\r" => Input::Key(Key::Confirm),
+        //Doors
+        b"e" => Input::Key(Key::Open),
+        b"c" => Input::Key(Key::Close),
+        //A lone escape quits, as in the window
+        b"\x1b" => Input::Escape,
+        _ => Input::Nothing,
+    }
+}
+
+/// Draw the part of the map around the player, followed by status lines
+fn draw(game: &Game, controls: &Controls) {
+    let map = game.map();
+    let (pi,pj) = game.player().coordinates();
+    let mut out = String::from("\x1b[H");
+    for row in 0..VIEW_HEIGHT {
+        for column in 0..VIEW_WIDTH {
+            let i = pi as isize + column - VIEW_WIDTH / 2;
+            let j = pj as isize + row - VIEW_HEIGHT / 2;
+            if i < 0 || j < 0 || i >= map.width() as isize || j >= map.height() as isize {
+                out.push(' ');
+                continue;
+            }
+            let (i,j) = (i as usize, j as usize);
+            let tile = map.tile((i,j));
+            // The targeting cursor is shown in inverse video
+            if controls.cursor() == Some((i,j)) { out.push_str("\x1b[7m") }
+            if (i,j) == (pi,pj) {
+                out.push_str("\x1b[1m@\x1b[0m");
+            } else if let Some(creature) = game.creatures().iter().find(|c| c.coordinates() == (i,j) && c.object.visible(map)) {
+                out.push_str("\x1b[1m");
+                out.push(creature.glyph);
+                out.push_str("\x1b[0m");
+            } else if tile.visible() && game.gold().iter().any(|&(c,_)| c == (i,j)) {
+                out.push_str("\x1b[33m$\x1b[0m");
+            } else if tile.visible() && *tile.terrain() == TerrainType::Torch {
+                out.push_str("\x1b[1;33m*\x1b[0m");
+            } else if tile.visible() && tile.light() < DIM_LIGHT {
+                out.push_str("\x1b[90m");
+                out.push(tile.terrain().glyph());
+                out.push_str("\x1b[0m");
+            } else if tile.visible() {
+                out.push(tile.terrain().glyph());
+            } else if tile.is_explored() {
+                // Remembered tiles are drawn dimmed, as the window shades them
+                out.push_str("\x1b[2m");
+                out.push(tile.terrain().glyph());
+                out.push_str("\x1b[0m");
+            } else {
+                out.push(' ');
+            }
+            if controls.cursor() == Some((i,j)) { out.push_str("\x1b[0m") }
+        }
+        // The terminal is in raw mode, so lines need an explicit carriage return
+        out.push_str("\r\n");
+    }
+    let player = game.player();
+    out.push_str(&format!("\x1b[KDepth {}  HP {}/{}  Mana {}/{}  Gold {}  Seed {}  Turn {}\r\n", game.depth(), player.hp, player.max_hp, player.mana, player.max_mana, player.gold, game.seed(), game.turn()));
+    // The last line shows the spellbook while choosing a spell, and the latest message otherwise
+    let status = match controls.mode {
+        Mode::ChooseSpell => player.spells.iter().enumerate().map(|(n,name)| format!("{}) {}", n+1, name)).collect::<Vec<_>>().join("  "),
+        Mode::Target(..) => "Aim with the movement keys, tab for the next creature, enter to cast".to_string(),
+        Mode::Open => "Open the door in which direction?".to_string(),
+        Mode::Close => "Close the door in which direction?".to_string(),
+        Mode::Play => game.messages().last().cloned().unwrap_or_default(),
+    };
+    out.push_str(&format!("\x1b[K{}", status));
+    print!("{}", out);
+    let _ = ::std::io::stdout().flush();
+}
+
+/// Draw the screen shown once the player has died
+fn draw_game_over(game: &Game) {
+    let mut out = String::from("\x1b[2J\x1b[H");
+    let lines = [
+        "\x1b[1mGAME OVER\x1b[0m".to_string(),
+        String::new(),
+        game.messages().last().cloned().unwrap_or_default(),
+        format!("You survived {} turns and reached level {}. (Seed {})", game.turn(), game.depth(), game.seed()),
+        String::new(),
+        "Press escape to quit.".to_string(),
+    ];
+    for (n,line) in lines.iter().enumerate() {
+        out.push_str(&format!("\x1b[{};4H{}", VIEW_HEIGHT / 2 - 3 + n as isize, line));
+    }
+    print!("{}", out);
+    let _ = ::std::io::stdout().flush();
+}
+
+/// Play the game in the terminal until the player quits. Replays are played at the given speed in turns
+/// per second, and commands the player issues are added to the recording.
+pub fn run(game: &mut Game, recording: &mut Option<Replay>, replay: &Option<Replay>, speed: f64) {
+    let _raw = RawMode::new();
+    print!("\x1b[2J");
+    let mut replayed = 0;                   // number of replay commands played so far
+    let mut timer = 0.0;                    // time since the last replay command
+    let mut last_update = Instant::now();
+    let mut controls = Controls::new();
+    loop {
+        if game.is_over() { draw_game_over(game) } else { draw(game, &controls) }
+        let key = match read_key() {
+            Input::Escape if controls.mode == Mode::Play => break,
+            // Outside normal play, escape backs out of the menu instead
+            Input::Escape => Some(Key::Cancel),
+            Input::Key(key) => Some(key),
+            Input::Nothing => None,
+        };
+        match key {
+            Some(key) if replay.is_none() => {
+                let command = controls.press(game, key);
+                if game.step(command) {
+                    if let Some(ref mut recording) = *recording { recording.record(command) }
+                }
+            }
+            _ => (),
+        }
+        if let Some(ref replay) = *replay {
+            // Reading a key can take a tenth of a second, so play every command that has come due since the
+            // last time round, as the window does
+            let elapsed = last_update.elapsed();
+            last_update = Instant::now();
+            timer += elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9;
+            while timer >= 1.0 / speed && replayed < replay.commands.len() {
+                timer -= 1.0 / speed;
+                game.step(replay.commands[replayed]);
+                replayed += 1;
+            }
+        }
+    }
+}